@@ -18,10 +18,7 @@ use iota_conversion::Trinary;
 #[smol_potat::main]
 async fn main() -> Result<()> {
     // Prepare a vector of transfers
-    let mut transfers = Vec::new();
-
-    // Push the transfer to vector.
-    transfers.push(Transfer {
+    let transfers = vec![Transfer {
         // Address is 81 trytes.
         address: Address::from_inner_unchecked(
             TryteBuf::try_from_str(
@@ -48,7 +45,7 @@ async fn main() -> Result<()> {
         .unwrap()
         .as_trits()
         .encode()).unwrap()),
-    });
+    }];
 
     // Create a client instance
     iota::Client::add_node("https://nodes.comnet.thetangle.org")?;
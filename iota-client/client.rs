@@ -1,55 +1,317 @@
 //! The Client module to connect through IRI with API usages
 use crate::core::*;
+use crate::error::Error;
 use crate::extended::*;
+use crate::health::{HealthPolicy, NodeHealth};
 use crate::response::*;
-use crate::util::tx_trytes;
+use crate::util::{transaction_hash, tx_trytes, validate_bundle};
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use iota_bundle_preview::{Address, Hash, Transaction, TransactionField};
 use iota_conversion::Trinary;
 use iota_crypto_preview::Kerl;
 use iota_signing_preview::IotaSeed;
-use iota_ternary_preview::TryteBuf;
 use once_cell::sync::Lazy;
 use reqwest::Url;
 
 macro_rules! response {
     ($self:ident, $body:ident) => {
-        $self
-            .client
-            .post(Client::get_node()?)
-            .header("Content-Type", "application/json")
-            .header("X-IOTA-API-Version", "1")
-            .body($body.to_string())
-            .send()
-            .await?
-            .json()
-            .await?
+        $self.send_json(Client::get_node()?, &$body).await?
     };
     ($self:ident, $body:ident, $node:ident) => {
-        $self
-            .client
-            .post($node)
-            .header("Content-Type", "application/json")
-            .header("X-IOTA-API-Version", "1")
-            .body($body.to_string())
-            .send()
-            .await?
-            .json()
-            .await?
+        $self.send_json($node, &$body).await?
     };
 }
 
+/// Maximum number of transactions of a bundle fetched by `traverse_bundle` by default
+pub const MAX_BUNDLE_LENGTH: usize = 256;
+
+/// Number of bundles `send_trytes` remembers by default, after which the oldest one is forgotten
+pub const MAX_SENT_BUNDLES: usize = 100;
+
+/// Maximum number of addresses sent in a single wereAddressesSpentFrom request
+pub const MAX_ADDRESSES_PER_REQUEST: usize = 1000;
+
+/// Maximum number of hashes sent in a single getTrytes request to nodes which don't advertise maxGetTrytes
+pub const DEFAULT_MAX_GET_TRYTES: usize = 1000;
+
+/// Maximum number of values per field sent in a single findTransactions request to nodes which don't
+/// advertise maxRequestsList
+pub const DEFAULT_MAX_REQUESTS_LIST: usize = 1000;
+
+/// Number of transactions sent in a single broadcastTransactions request by default
+pub const DEFAULT_BROADCAST_CHUNK_SIZE: usize = 20;
+
+/// Maximum number of broadcastTransactions requests of a bundle sent at the same time
+pub const MAX_CONCURRENT_BROADCASTS: usize = 4;
+
+/// Maximum number of getTrytes requests of `get_trytes_stream` sent at the same time
+pub const MAX_CONCURRENT_GET_TRYTES: usize = 4;
+
+/// Number of characters of a response which can't be parsed included in the error
+const RESPONSE_SNIPPET_LEN: usize = 200;
+
+/// Value of the X-IOTA-API-Version header sent by default
+pub const DEFAULT_API_VERSION: &str = "1";
+
+/// Number of tip selections sampled by `get_confirmation_confidence`
+pub const CONFIDENCE_SAMPLES: usize = 10;
+
+/// Depth `Depth::Auto` starts from when the node is in sync
+pub const DEFAULT_DEPTH: u8 = 3;
+
+/// Maximum depth `Depth::Auto` picks, which is the default maximum depth of IRI
+pub const MAX_AUTO_DEPTH: u8 = 15;
+
+/// Depth of the random walk of the tip selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Depth {
+    /// [`DEFAULT_DEPTH`] plus the number of milestones the node hasn't solidified yet, up to
+    /// [`MAX_AUTO_DEPTH`], so a lagging node walks back far enough. The node info is fetched once
+    /// per call which needs the depth, and failing to fetch it fails the call.
+    ///
+    /// [`DEFAULT_DEPTH`]: constant.DEFAULT_DEPTH.html
+    /// [`MAX_AUTO_DEPTH`]: constant.MAX_AUTO_DEPTH.html
+    Auto,
+    /// The given number of milestones. It is a `u8` rather than a `usize`, as nodes reject depths
+    /// above their maximum depth, which is 15 by default and far below 255 in practice.
+    Fixed(u8),
+}
+
+impl From<u8> for Depth {
+    fn from(depth: u8) -> Self {
+        Depth::Fixed(depth)
+    }
+}
+
+impl Depth {
+    /// Resolves `Auto` from the node info of a node in the pool
+    pub(crate) async fn resolve(self) -> Result<u8> {
+        match self {
+            Depth::Fixed(depth) => Ok(depth),
+            Depth::Auto => {
+                let info = Client::get_node_info().await?;
+                let lag = info
+                    .latest_milestone_index
+                    .saturating_sub(info.latest_solid_subtangle_milestone_index);
+                // Milestone indexes are node-reported, so the lag can be anything
+                Ok((DEFAULT_DEPTH as u32)
+                    .saturating_add(lag)
+                    .min(MAX_AUTO_DEPTH as u32) as u8)
+            }
+        }
+    }
+}
+
+/// Timeouts of the requests sent to nodes
+#[derive(Clone, Copy, Debug)]
+pub struct Timeouts {
+    /// Timeout of all commands without a specific timeout. Default is 30 seconds.
+    pub default: Duration,
+    /// Timeout of attachToTangle, which does the PoW on the node. Default is 60 seconds.
+    pub attach: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            default: Duration::from_secs(30),
+            attach: Duration::from_secs(60),
+        }
+    }
+}
+
+/// How the HTTP connections to nodes are kept open and reused between requests
+#[derive(Clone, Copy, Debug)]
+pub struct ConnectionPool {
+    /// How long an idle connection is kept open, `None` keeps it open until the node closes it.
+    /// Default is 90 seconds.
+    pub idle_timeout: Option<Duration>,
+    /// Maximum number of idle connections kept open per node, 0 opens a new connection for every
+    /// request. Default is unlimited.
+    pub max_idle_per_host: usize,
+}
+
+impl Default for ConnectionPool {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Some(Duration::from_secs(90)),
+            max_idle_per_host: usize::MAX,
+        }
+    }
+}
+
+impl ConnectionPool {
+    fn build_client(&self) -> reqwest::Client {
+        #[cfg(not(target_arch = "wasm32"))]
+        let client = reqwest::Client::builder()
+            .pool_idle_timeout(self.idle_timeout)
+            .pool_max_idle_per_host(self.max_idle_per_host)
+            .build()
+            .expect("Could not build the HTTP client");
+        #[cfg(target_arch = "wasm32")]
+        let client = reqwest::Client::new();
+        client
+    }
+}
+
+/// Request limits of a node, from its API configuration
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ApiLimits {
+    pub(crate) max_get_trytes: usize,
+    pub(crate) max_requests_list: usize,
+}
+
+impl ApiLimits {
+    fn new(config: Option<GetNodeAPIConfigurationResponse>) -> Self {
+        let limit = |limit: Option<usize>, default| match limit {
+            Some(limit) if limit > 0 => limit,
+            _ => default,
+        };
+        let config = config.as_ref();
+        Self {
+            max_get_trytes: limit(
+                config.and_then(|c| c.max_get_trytes),
+                DEFAULT_MAX_GET_TRYTES,
+            ),
+            max_requests_list: limit(
+                config.and_then(|c| c.max_requests_list),
+                DEFAULT_MAX_REQUESTS_LIST,
+            ),
+        }
+    }
+}
+
+/// Callback invoked with the JSON of requests or responses
+pub(crate) type Hook = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Callbacks set with `on_request` and `on_response`
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    pub(crate) request: Option<Hook>,
+    pub(crate) response: Option<Hook>,
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("request", &self.request.is_some())
+            .field("response", &self.response.is_some())
+            .finish()
+    }
+}
+
+/// Tips of the last getTransactionsToApprove request
+#[derive(Clone, Debug)]
+pub(crate) struct CachedTips {
+    pub(crate) body: serde_json::Value,
+    pub(crate) tips: GTTAResponse,
+    pub(crate) expires: DateTime<Utc>,
+}
+
+/// Attached transactions of the bundles `send_trytes` has sent, oldest first, and the bundles it
+/// is sending right now
+#[derive(Debug)]
+pub(crate) struct SentBundles {
+    pub(crate) max: usize,
+    bundles: HashMap<Hash, Vec<Transaction>>,
+    order: VecDeque<Hash>,
+    in_flight: HashSet<Hash>,
+}
+
+impl SentBundles {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            max,
+            bundles: HashMap::new(),
+            order: VecDeque::new(),
+            in_flight: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn get(&self, bundle: &Hash) -> Option<&Vec<Transaction>> {
+        self.bundles.get(bundle)
+    }
+
+    /// Mark a bundle as being sent, false if it is being sent already
+    pub(crate) fn reserve(&mut self, bundle: Hash) -> bool {
+        self.in_flight.insert(bundle)
+    }
+
+    pub(crate) fn release(&mut self, bundle: &Hash) {
+        self.in_flight.remove(bundle);
+    }
+
+    /// Remember a bundle, forgetting the oldest ones past `max`
+    pub(crate) fn insert(&mut self, bundle: Hash, transactions: Vec<Transaction>) {
+        if self.bundles.insert(bundle, transactions).is_none() {
+            self.order.push_back(bundle);
+        }
+        self.evict();
+    }
+
+    pub(crate) fn set_max(&mut self, max: usize) {
+        self.max = max;
+        self.evict();
+    }
+
+    /// Forget the sent bundles. Bundles being sent stay reserved until their calls finish.
+    pub(crate) fn clear(&mut self) {
+        self.bundles.clear();
+        self.order.clear();
+    }
+
+    fn evict(&mut self) {
+        while self.order.len() > self.max {
+            if let Some(oldest) = self.order.pop_front() {
+                self.bundles.remove(&oldest);
+            }
+        }
+    }
+}
+
 /// An instance of the client using IRI URI
 #[derive(Debug)]
 pub struct Client {
     // Node pool of IOTA nodes
     pub(crate) pool: Arc<RwLock<HashSet<Url>>>,
-    /// A reqwest Client to make Requests with
-    pub(crate) client: reqwest::Client,
+    // Value of the X-IOTA-API-Version header
+    pub(crate) api_version: Arc<RwLock<String>>,
+    // Timeouts of the requests
+    pub(crate) timeouts: Arc<RwLock<Timeouts>>,
+    // Health of the nodes which have been requested
+    pub(crate) health: Arc<RwLock<HashMap<Url, NodeHealth>>>,
+    // When failing nodes get benched
+    pub(crate) health_policy: Arc<RwLock<HealthPolicy>>,
+    // Number of other nodes asked for transactions unknown to the first node
+    pub(crate) trytes_retries: Arc<RwLock<usize>>,
+    // Maximum number of transactions of a bundle fetched by traverse_bundle
+    pub(crate) max_bundle_length: Arc<RwLock<usize>>,
+    // How long tips of getTransactionsToApprove are reused
+    pub(crate) tips_cache_ttl: Arc<RwLock<Duration>>,
+    // Tips of the last getTransactionsToApprove request
+    pub(crate) tips_cache: Arc<RwLock<Option<CachedTips>>>,
+    // Number of transactions sent in a single broadcastTransactions request
+    pub(crate) broadcast_chunk_size: Arc<RwLock<usize>>,
+    // Addresses nodes have reported as spent
+    pub(crate) spent_addresses: Arc<RwLock<HashSet<String>>>,
+    // Attached transactions of the last bundles send_trytes has stored and broadcast, by bundle hash
+    pub(crate) sent_bundles: Arc<RwLock<SentBundles>>,
+    // Features advertised by the nodes which have been requested, None if a node doesn't list them
+    pub(crate) features: Arc<RwLock<HashMap<Url, Option<HashSet<String>>>>>,
+    // Callbacks invoked with the JSON of requests and responses
+    pub(crate) hooks: Arc<RwLock<Hooks>>,
+    // Request limits of the nodes which have been requested
+    pub(crate) api_limits: Arc<RwLock<HashMap<Url, ApiLimits>>>,
+    /// A reqwest Client to make Requests with, which reuses its connections to the nodes
+    pub(crate) client: Arc<RwLock<reqwest::Client>>,
 }
 
 impl Client {
@@ -57,7 +319,21 @@ impl Client {
     pub fn get() -> &'static Client {
         static CLIENT: Lazy<Client> = Lazy::new(|| Client {
             pool: Arc::new(RwLock::new(HashSet::new())),
-            client: reqwest::Client::new(),
+            api_version: Arc::new(RwLock::new(DEFAULT_API_VERSION.to_string())),
+            timeouts: Arc::new(RwLock::new(Timeouts::default())),
+            health: Arc::new(RwLock::new(HashMap::new())),
+            health_policy: Arc::new(RwLock::new(HealthPolicy::default())),
+            trytes_retries: Arc::new(RwLock::new(0)),
+            max_bundle_length: Arc::new(RwLock::new(MAX_BUNDLE_LENGTH)),
+            tips_cache_ttl: Arc::new(RwLock::new(Duration::from_secs(0))),
+            tips_cache: Arc::new(RwLock::new(None)),
+            broadcast_chunk_size: Arc::new(RwLock::new(DEFAULT_BROADCAST_CHUNK_SIZE)),
+            spent_addresses: Arc::new(RwLock::new(HashSet::new())),
+            sent_bundles: Arc::new(RwLock::new(SentBundles::new(MAX_SENT_BUNDLES))),
+            features: Arc::new(RwLock::new(HashMap::new())),
+            hooks: Arc::new(RwLock::new(Hooks::default())),
+            api_limits: Arc::new(RwLock::new(HashMap::new())),
+            client: Arc::new(RwLock::new(ConnectionPool::default().build_client())),
         });
 
         &CLIENT
@@ -71,22 +347,413 @@ impl Client {
         Ok(set.insert(url))
     }
 
+    /// Add the node whose URL is stored in the given environment variable to the node pool.
+    /// The URL must use the http or https scheme.
+    pub fn add_node_from_env(var: &str) -> Result<bool> {
+        let uri =
+            std::env::var(var).map_err(|e| anyhow!("Fail to read node URL from {}: {}", var, e))?;
+        let url =
+            Url::parse(&uri).map_err(|e| anyhow!("Invalid node URL {} in {}: {}", uri, var, e))?;
+        match url.scheme() {
+            "http" | "https" => Client::add_node(url.as_str()),
+            scheme => Err(anyhow!(
+                "Invalid node URL {} in {}: scheme should be http or https, not {}",
+                uri,
+                var,
+                scheme
+            )),
+        }
+    }
+
     /// Remove a node from the node pool.
     pub fn remove_node(uri: &str) -> Result<bool> {
         let url = Url::parse(uri)?;
         let pool = Client::get().pool.clone();
         let mut set = pool.write().expect("Node pool write poisened");
+        let health = Client::get().health.clone();
+        health
+            .write()
+            .expect("Node health write poisened")
+            .remove(&url);
+        let api_limits = Client::get().api_limits.clone();
+        api_limits
+            .write()
+            .expect("API limits write poisened")
+            .remove(&url);
+        let features = Client::get().features.clone();
+        features
+            .write()
+            .expect("Node features write poisened")
+            .remove(&url);
         Ok(set.remove(&url))
     }
 
+    /// Get the health state of all nodes in the node pool.
+    pub fn node_health() -> Result<Vec<NodeHealth>> {
+        let client = Client::get();
+        let pool = client
+            .pool
+            .read()
+            .map_err(|_| anyhow!("Node pool read poinsened"))?;
+        let health = client
+            .health
+            .read()
+            .map_err(|_| anyhow!("Node health read poisened"))?;
+        Ok(pool
+            .iter()
+            .map(|url| {
+                health
+                    .get(url)
+                    .cloned()
+                    .unwrap_or_else(|| NodeHealth::new(url.clone()))
+            })
+            .collect())
+    }
+
+    /// Set after how many consecutive failures a node is benched and for how long.
+    /// Benched nodes are only used if there is no other node left.
+    pub fn set_health_policy(policy: HealthPolicy) {
+        let lock = Client::get().health_policy.clone();
+        let mut current = lock.write().expect("Health policy write poisened");
+        *current = policy;
+    }
+
+    /// Set how long the tips of [`get_transactions_to_approve`] are reused for requests with the same
+    /// parameters, so bursts of zero value attachments don't request tips for each bundle.
+    /// Value transfers always request new tips. Default is 0, which disables the cache.
+    ///
+    /// [`get_transactions_to_approve`]: #method.get_transactions_to_approve
+    pub fn set_tips_cache_ttl(ttl: Duration) {
+        let lock = Client::get().tips_cache_ttl.clone();
+        let mut current = lock.write().expect("Tips cache TTL write poisened");
+        *current = ttl;
+    }
+
+    /// Forget the addresses [`were_addresses_spent_from`] has seen as spent, e.g. after switching to
+    /// nodes of another network.
+    ///
+    /// [`were_addresses_spent_from`]: #method.were_addresses_spent_from
+    pub fn clear_spent_addresses() {
+        let lock = Client::get().spent_addresses.clone();
+        lock.write()
+            .expect("Spent addresses write poisened")
+            .clear();
+    }
+
+    /// Forget the bundles [`send_trytes`] has sent, so sending them again attaches them again.
+    ///
+    /// [`send_trytes`]: #method.send_trytes
+    pub fn clear_sent_bundles() {
+        let lock = Client::get().sent_bundles.clone();
+        lock.write().expect("Sent bundles write poisened").clear();
+    }
+
+    /// Set how many bundles [`send_trytes`] remembers. Once more have been sent the oldest one is
+    /// forgotten, so sending it again attaches it again. 0 disables the check. Default is
+    /// [`MAX_SENT_BUNDLES`].
+    ///
+    /// [`send_trytes`]: #method.send_trytes
+    /// [`MAX_SENT_BUNDLES`]: constant.MAX_SENT_BUNDLES.html
+    pub fn set_max_sent_bundles(max: usize) {
+        let lock = Client::get().sent_bundles.clone();
+        lock.write()
+            .expect("Sent bundles write poisened")
+            .set_max(max);
+    }
+
+    /// Set how many transactions [`broadcast_transactions`] sends in a single request, so large
+    /// bundles don't exceed the request body limit of nodes. Default is [`DEFAULT_BROADCAST_CHUNK_SIZE`].
+    ///
+    /// [`broadcast_transactions`]: #method.broadcast_transactions
+    /// [`DEFAULT_BROADCAST_CHUNK_SIZE`]: constant.DEFAULT_BROADCAST_CHUNK_SIZE.html
+    pub fn set_broadcast_chunk_size(size: usize) {
+        let lock = Client::get().broadcast_chunk_size.clone();
+        let mut current = lock.write().expect("Broadcast chunk size write poisened");
+        *current = size.max(1);
+    }
+
+    /// Set a callback which is invoked with the JSON body of every request sent to a node, e.g. to
+    /// log it. Requests are node API commands, seeds and private keys never leave the client, so
+    /// they don't appear in the bodies.
+    pub fn on_request<F: Fn(&str) + Send + Sync + 'static>(hook: F) {
+        let lock = Client::get().hooks.clone();
+        let mut hooks = lock.write().expect("Hooks write poisened");
+        hooks.request = Some(Arc::new(hook));
+    }
+
+    /// Set a callback which is invoked with the JSON body of every response the client reads from a
+    /// node, e.g. to log it.
+    pub fn on_response<F: Fn(&str) + Send + Sync + 'static>(hook: F) {
+        let lock = Client::get().hooks.clone();
+        let mut hooks = lock.write().expect("Hooks write poisened");
+        hooks.response = Some(Arc::new(hook));
+    }
+
+    /// Remove the callbacks set with [`on_request`] and [`on_response`].
+    ///
+    /// [`on_request`]: #method.on_request
+    /// [`on_response`]: #method.on_response
+    pub fn clear_hooks() {
+        let lock = Client::get().hooks.clone();
+        let mut hooks = lock.write().expect("Hooks write poisened");
+        *hooks = Hooks::default();
+    }
+
+    /// Set the API version sent to nodes in the X-IOTA-API-Version header of every request. Nodes
+    /// rejecting it make requests fail with [`Error::MissingApiVersion`]. Default is
+    /// [`DEFAULT_API_VERSION`].
+    ///
+    /// [`Error::MissingApiVersion`]: ../error/enum.Error.html#variant.MissingApiVersion
+    /// [`DEFAULT_API_VERSION`]: constant.DEFAULT_API_VERSION.html
+    pub fn set_api_version(version: &str) {
+        let lock = Client::get().api_version.clone();
+        let mut current = lock.write().expect("API version write poisened");
+        *current = version.to_string();
+    }
+
+    /// Set the timeouts of the requests sent to nodes.
+    pub fn set_timeouts(timeouts: Timeouts) {
+        let lock = Client::get().timeouts.clone();
+        let mut current = lock.write().expect("Timeouts write poisened");
+        *current = timeouts;
+    }
+
+    /// Set how the HTTP connections to nodes are reused. Open connections are closed, as requests
+    /// made after this use a new HTTP client.
+    pub fn set_connection_pool(pool: ConnectionPool) {
+        let lock = Client::get().client.clone();
+        let mut current = lock.write().expect("HTTP client write poisened");
+        *current = pool.build_client();
+    }
+
+    /// Set how many other nodes of the pool [`get_trytes`] asks for transactions the first node returned
+    /// as unknown, before reporting them as `None`. Default is 0.
+    ///
+    /// [`get_trytes`]: #method.get_trytes
+    pub fn set_trytes_retries(retries: usize) {
+        let lock = Client::get().trytes_retries.clone();
+        let mut current = lock.write().expect("Trytes retries write poisened");
+        *current = retries;
+    }
+
+    /// Set the maximum number of transactions of a bundle [`traverse_bundle`] fetches. Longer
+    /// bundles fail with [`Error::BundleTooLong`]. Default is [`MAX_BUNDLE_LENGTH`].
+    ///
+    /// [`traverse_bundle`]: #method.traverse_bundle
+    /// [`Error::BundleTooLong`]: ../error/enum.Error.html#variant.BundleTooLong
+    /// [`MAX_BUNDLE_LENGTH`]: constant.MAX_BUNDLE_LENGTH.html
+    pub fn set_max_bundle_length(length: usize) {
+        let lock = Client::get().max_bundle_length.clone();
+        let mut current = lock.write().expect("Max bundle length write poisened");
+        *current = length;
+    }
+
+    /// Build the request of an API call to the given node.
+    pub(crate) fn post(
+        &self,
+        node: Url,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::RequestBuilder> {
+        let version = self
+            .api_version
+            .read()
+            .map_err(|_| anyhow!("API version read poisened"))?
+            .clone();
+        let request = self
+            .client
+            .read()
+            .map_err(|_| anyhow!("HTTP client read poisened"))?
+            .post(node)
+            .header("Content-Type", "application/json")
+            .header("X-IOTA-API-Version", version)
+            .body(body.to_string());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let request = {
+            let timeouts = *self
+                .timeouts
+                .read()
+                .map_err(|_| anyhow!("Timeouts read poisened"))?;
+            request.timeout(match body["command"].as_str() {
+                Some("attachToTangle") => timeouts.attach,
+                _ => timeouts.default,
+            })
+        };
+
+        Ok(request)
+    }
+
+    /// Send an API call to the given node and update its health.
+    pub(crate) async fn send(
+        &self,
+        node: Url,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response> {
+        // The hook is cloned out of the lock, so it may set hooks itself
+        let hook = self
+            .hooks
+            .read()
+            .map_err(|_| anyhow!("Hooks read poisened"))?
+            .request
+            .clone();
+        if let Some(hook) = hook {
+            hook(&body.to_string());
+        }
+
+        let res = self.post(node.clone(), body)?.send().await;
+        let success = match &res {
+            Ok(response) => !response.status().is_server_error(),
+            Err(_) => false,
+        };
+
+        let policy = *self
+            .health_policy
+            .read()
+            .map_err(|_| anyhow!("Health policy read poisened"))?;
+        self.health
+            .write()
+            .map_err(|_| anyhow!("Node health write poisened"))?
+            .entry(node.clone())
+            .or_insert_with(|| NodeHealth::new(node))
+            .record(success, &policy);
+
+        Ok(res?)
+    }
+
+    /// Send an API call to the given node and parse the response.
+    pub(crate) async fn send_json<T: serde::de::DeserializeOwned>(
+        &self,
+        node: Url,
+        body: &serde_json::Value,
+    ) -> Result<T> {
+        let res = self.send(node, body).await?;
+        self.read_json(res, body).await
+    }
+
+    /// Parse the response of an API call. Responses which can't be parsed fail with
+    /// `Error::Deserialization`, naming the command and the start of the response.
+    pub(crate) async fn read_json<T: serde::de::DeserializeOwned>(
+        &self,
+        res: reqwest::Response,
+        body: &serde_json::Value,
+    ) -> Result<T> {
+        let text = res.text().await?;
+        let hook = self
+            .hooks
+            .read()
+            .map_err(|_| anyhow!("Hooks read poisened"))?
+            .response
+            .clone();
+        if let Some(hook) = hook {
+            hook(&text);
+        }
+
+        let invalid = || Error::Deserialization {
+            command: body["command"].as_str().unwrap_or_default().to_string(),
+            snippet: text.chars().take(RESPONSE_SNIPPET_LEN).collect(),
+        };
+        let json: serde_json::Value = serde_json::from_str(&text).map_err(|_| invalid())?;
+        // IRI answers "Invalid API Version" to requests without a header it accepts
+        if let Some(error) = json["error"].as_str() {
+            if error.contains("API Version") {
+                let version = self
+                    .api_version
+                    .read()
+                    .map_err(|_| anyhow!("API version read poisened"))?
+                    .clone();
+                return Err(Error::MissingApiVersion(version).into());
+            }
+        }
+        Ok(serde_json::from_value(json).map_err(|_| invalid())?)
+    }
+
+    // Fetches the request limits of a node once. Nodes without a usable API configuration get the defaults.
+    pub(crate) async fn api_limits(&self, node: &Url) -> Result<ApiLimits> {
+        if let Some(limits) = self
+            .api_limits
+            .read()
+            .map_err(|_| anyhow!("API limits read poisened"))?
+            .get(node)
+        {
+            return Ok(*limits);
+        }
+
+        let body = json!({
+            "command": "getNodeAPIConfiguration",
+        });
+        let res = self.send(node.clone(), &body).await?;
+        let config = self.read_json(res, &body).await.ok();
+        let limits = ApiLimits::new(config);
+        self.api_limits
+            .write()
+            .map_err(|_| anyhow!("API limits write poisened"))?
+            .insert(node.clone(), limits);
+        Ok(limits)
+    }
+
+    // Fails with UnsupportedCommand if the node lists its features without the one the command
+    // needs. The features are fetched from getNodeInfo once per node, nodes which don't list any
+    // are assumed to support every command.
+    pub(crate) async fn require_command(&self, node: &Url, command: &str) -> Result<()> {
+        let feature = match command {
+            "attachToTangle" => "RemotePOW",
+            "wereAddressesSpentFrom" => "WereAddressesSpentFrom",
+            _ => return Ok(()),
+        };
+
+        let cached = self
+            .features
+            .read()
+            .map_err(|_| anyhow!("Node features read poisened"))?
+            .get(node)
+            .cloned();
+        let features = match cached {
+            Some(features) => features,
+            None => {
+                let body = json!({
+                    "command": "getNodeInfo",
+                });
+                // Without node info the command is just sent, so a failing request isn't cached
+                let info: Option<GetNodeInfoResponse> = match self.send(node.clone(), &body).await {
+                    Ok(res) => self.read_json(res, &body).await.ok(),
+                    Err(_) => return Ok(()),
+                };
+                let features = info
+                    .and_then(|info| info.features)
+                    .map(|features| features.into_iter().collect());
+                self.features
+                    .write()
+                    .map_err(|_| anyhow!("Node features write poisened"))?
+                    .insert(node.clone(), features.clone());
+                features
+            }
+        };
+
+        match features {
+            Some(features) if !features.contains(feature) => {
+                Err(Error::UnsupportedCommand(command.to_string()).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Picks the node with the fewest consecutive failures, benched nodes come last.
     pub(crate) fn get_node() -> Result<Url> {
-        Ok(Client::get()
+        let client = Client::get();
+        let pool = client
             .pool
-            .clone()
             .read()
-            .map_err(|_| anyhow!("Node pool read poinsened"))?
+            .map_err(|_| anyhow!("Node pool read poinsened"))?;
+        let health = client
+            .health
+            .read()
+            .map_err(|_| anyhow!("Node health read poisened"))?;
+        Ok(pool
             .iter()
-            .next()
+            .min_by_key(|url| match health.get(url) {
+                Some(h) => (h.is_benched(), h.consecutive_failures),
+                None => (false, 0),
+            })
             .ok_or(anyhow!("No node available"))?
             .clone())
     }
@@ -100,7 +767,7 @@ impl Client {
     /// [`uris`]: ../core/struct.AddNeighborsBuilder.html#method.uris
     pub async fn add_neighbors(uris: Vec<String>) -> Result<AddNeighborsResponse> {
         for uri in &uris {
-            match Url::parse(&uri)?.scheme() {
+            match Url::parse(uri)?.scheme() {
                 "tcp" | "udp" => (),
                 _ => return Err(anyhow!("Uri scheme should be either tcp or udp")),
             }
@@ -123,7 +790,7 @@ impl Client {
     /// * [`branch_transaction`] - branch transaction hash
     /// * [`min_weight_magnitude`] - Difficulty of PoW
     /// * [`trytes`] - Slice of transaction trytes. When sending transactions in a bundle,
-    /// make sure that the trytes of the last transaction in the bundle are in index 0 of the array.
+    ///   make sure that the trytes of the last transaction in the bundle are in index 0 of the array.
     ///
     /// [`trunk_transaction`]: ../core/struct.AttachToTangleBuilder.html#method.trunk_transaction
     /// [`branch_transaction`]: ../core/struct.AttachToTangleBuilder.html#method.branch_transaction
@@ -149,19 +816,46 @@ impl Client {
     /// Sends transaction trytes to a node.
     /// The input trytes for this call are provided by `attach_to_tangle`.
     /// Response only contains errors and exceptions, it would be `None` if the call success.
+    /// Large bundles are split into requests of the size set by [`set_broadcast_chunk_size`], which are
+    /// sent with at most [`MAX_CONCURRENT_BROADCASTS`] at a time. The chunk with the last transactions,
+    /// which holds the tail in bundle order, is only sent after all others succeeded.
+    /// Nodes answering that they already know the transactions count as success.
     /// # Parameters
     /// * [`trytes`] - Transaction trytes
     ///
     /// [`trytes`]: ../core/struct.BroadcastTransactionsBuilder.html#method.trytes
+    /// [`set_broadcast_chunk_size`]: #method.set_broadcast_chunk_size
+    /// [`MAX_CONCURRENT_BROADCASTS`]: constant.MAX_CONCURRENT_BROADCASTS.html
     pub async fn broadcast_transactions(trytes: &[Transaction]) -> Result<()> {
         let client = Client::get();
-        let trytes: Vec<String> = trytes.iter().map(|tx| tx_trytes(tx)).collect();
+        let node = Client::get_node()?;
+        let size = *client
+            .broadcast_chunk_size
+            .read()
+            .map_err(|_| anyhow!("Broadcast chunk size read poisened"))?;
+        let trytes: Vec<String> = trytes.iter().map(tx_trytes).collect();
+        let mut chunks: Vec<&[String]> = trytes.chunks(size).collect();
+        let last = chunks.pop().unwrap_or(&[]);
+
+        stream::iter(chunks)
+            .map(|chunk| client.broadcast_chunk(node.clone(), chunk))
+            .buffer_unordered(MAX_CONCURRENT_BROADCASTS)
+            .try_collect::<Vec<()>>()
+            .await?;
+        client.broadcast_chunk(node, last).await
+    }
+
+    async fn broadcast_chunk(&self, node: Url, trytes: &[String]) -> Result<()> {
         let body = json!({
             "command": "broadcastTransactions",
             "trytes": trytes,
         });
 
-        let res: ErrorResponseBuilder = response!(client, body);
+        let res: ErrorResponseBuilder = response!(self, body, node);
+        // Broadcasting is idempotent, a node which knows the transactions already has them
+        if res.is_duplicate() {
+            return Ok(());
+        }
         res.build().await
     }
 
@@ -196,11 +890,13 @@ impl Client {
     /// * [`addresses`] - (Optional) Addresses to search for (do not include the checksum)
     /// * [`tags`] - (Optional) Tags to search for
     /// * [`approvees`] - (Optional) Child transactions to search for
+    /// * [`sorted`] - (Optional) Sort the hashes by their trytes, default is false
     ///
     /// [`bundles`]: ../core/struct.FindTransactionsBuilder.html#method.bundles
     /// [`addresses`]: ../core/struct.FindTransactionsBuilder.html#method.addresses
     /// [`tags`]: ../core/struct.FindTransactionsBuilder.html#method.tags
     /// [`approvees`]: ../core/struct.FindTransactionsBuilder.html#method.approvees
+    /// [`sorted`]: ../core/struct.FindTransactionsBuilder.html#method.sorted
     pub fn find_transactions() -> FindTransactionsBuilder {
         FindTransactionsBuilder::new()
     }
@@ -208,6 +904,7 @@ impl Client {
     /// Gets the confirmed balance of an address.
     /// If the tips parameter is missing, the returned balance is correct as of the latest confirmed milestone.
     /// This endpoint returns data only if the node is synchronized.
+    /// Without addresses, no balances are returned and no request is sent.
     /// # Parameters
     /// * [`addresses`] - Address for which to get the balance (do not include the checksum)
     /// * [`threshold`] - (Optional) Confirmation threshold between 0 and 100, default is 100.
@@ -227,11 +924,110 @@ impl Client {
     ///
     /// [`traverse_bundle`]: #method.traverse_bundle
     pub async fn get_bundle(hash: &Hash) -> Result<Vec<Transaction>> {
-        // TODO validate bundle once it's in iota_bundle_preview's bundle types
         let bundle = Client::traverse_bundle(hash).await?;
+        validate_bundle(&bundle)?;
         Ok(bundle)
     }
 
+    /// Fetches the transactions of the given hashes and groups them into bundles, in the order the
+    /// bundles first appear in `hashes`. Every tail among the hashes starts its own bundle, so
+    /// reattachments of the same bundle are returned separately. Bundles the hashes don't fully
+    /// cover are completed by walking the trunks from their tail, which has to be among the hashes.
+    /// Every bundle is validated like in [`get_bundle`].
+    /// # Parameters
+    /// * `hashes` - Transaction hashes
+    ///
+    /// [`get_bundle`]: #method.get_bundle
+    pub async fn get_trytes_as_bundles(hashes: &[Hash]) -> Result<Vec<Vec<Transaction>>> {
+        let transactions = Client::get_trytes(hashes).await?.trytes;
+        let mut fetched = HashMap::new();
+        let mut order = Vec::new();
+        for (hash, tx) in hashes.iter().zip(transactions) {
+            let tx = match tx {
+                Some(tx) => tx,
+                None => {
+                    let hash = hash.as_bytes().trytes().map_err(|e| anyhow!("{}", e))?;
+                    return Err(anyhow!("Transaction {} not found", hash));
+                }
+            };
+            if fetched.insert(*hash, tx).is_none() {
+                order.push(*hash);
+            }
+        }
+
+        // Each tail follows its trunks through the fetched transactions as long as they continue
+        // its bundle. Indexes grow with every step, so the walk ends at the last index.
+        let mut groups: Vec<Vec<Hash>> = Vec::new();
+        let mut owners = HashMap::new();
+        for hash in &order {
+            let tail = &fetched[hash];
+            if *tail.index().to_inner() != 0 {
+                continue;
+            }
+            let mut group = vec![*hash];
+            let mut current = tail;
+            while current.index() != current.last_index() {
+                match fetched.get(current.trunk()) {
+                    Some(next)
+                        if *next.index().to_inner() == group.len()
+                            && next.last_index() == tail.last_index()
+                            && next.bundle() == tail.bundle() =>
+                    {
+                        group.push(*current.trunk());
+                        current = next;
+                    }
+                    _ => break,
+                }
+            }
+            for member in &group {
+                owners.entry(*member).or_insert(groups.len());
+            }
+            groups.push(group);
+        }
+
+        let mut returned = vec![false; groups.len()];
+        let mut bundles = Vec::with_capacity(groups.len());
+        for hash in &order {
+            let tx = &fetched[hash];
+            // Transactions no trunk walk reached are covered by the first tail of their bundle
+            let group = match owners.get(hash).copied().or_else(|| {
+                groups
+                    .iter()
+                    .position(|group| fetched[&group[0]].bundle() == tx.bundle())
+            }) {
+                Some(group) => group,
+                None => {
+                    let bundle = tx
+                        .bundle()
+                        .as_bytes()
+                        .trytes()
+                        .map_err(|e| anyhow!("{}", e))?;
+                    return Err(anyhow!("Tail transaction of bundle {} not found", bundle));
+                }
+            };
+            if std::mem::replace(&mut returned[group], true) {
+                continue;
+            }
+
+            // The hashes cover the bundle if the walk reached the last index
+            let group = &groups[group];
+            let tail = &fetched[&group[0]];
+            let complete = group.len() == *tail.last_index().to_inner() + 1
+                && group
+                    .iter()
+                    .all(|hash| transaction_hash(&fetched[hash]) == *hash);
+            if complete {
+                let bundle: Vec<Transaction> =
+                    group.iter().map(|hash| fetched[hash].clone()).collect();
+                validate_bundle(&bundle)?;
+                bundles.push(bundle);
+            } else {
+                bundles.push(Client::get_bundle(&group[0]).await?);
+            }
+        }
+        Ok(bundles)
+    }
+
     /// Gets the inclusion states of a set of transactions.
     /// This endpoint determines if a transaction is confirmed by the network (referenced by a valid milestone).
     /// You can search for multiple tips (and thus, milestones) to get past inclusion states of transactions.
@@ -263,33 +1059,96 @@ impl Client {
 
     /// Fetches inclusion states of the given transactions by calling GetInclusionStates
     /// using the latest solid subtangle milestone from GetNodeInfo.
+    /// Nodes which dropped GetInclusionStates make this return [`Error::UnsupportedCommand`],
+    /// since a consistency check alone can't tell whether a transaction is confirmed.
     ///
     /// # Parameters
     /// * [`transactions`] - List of transaction hashes for which you want to get the inclusion state
+    ///
+    /// [`Error::UnsupportedCommand`]: ../error/enum.Error.html#variant.UnsupportedCommand
     pub async fn get_latest_inclusion(transactions: &[Hash]) -> Result<Vec<bool>> {
-        let milestone = Client::get_latest_solid_subtangle_milestone().await?;
+        let milestone = Client::get_latest_solid_milestone().await?;
         let states = Client::get_inclusion_states()
             .transactions(transactions)
-            .tips(&[milestone])
+            .tips(&[milestone.hash])
             .send()
             .await?
             .states;
         Ok(states)
     }
 
+    /// Estimates how likely pending tail transactions are to get confirmed, as the fraction of
+    /// tips referencing them out of [`CONFIDENCE_SAMPLES`] tip selections with two tips each.
+    /// Tails which are already confirmed by the latest solid milestone have a confidence of 1.0.
+    ///
+    /// # Parameters
+    /// * `tails` - Tail transaction hashes
+    ///
+    /// [`CONFIDENCE_SAMPLES`]: constant.CONFIDENCE_SAMPLES.html
+    pub async fn get_confirmation_confidence(tails: &[Hash]) -> Result<Vec<f64>> {
+        let confirmed = Client::get_latest_inclusion(tails).await?;
+        let pending: Vec<Hash> = tails
+            .iter()
+            .zip(&confirmed)
+            .filter(|(_, confirmed)| !**confirmed)
+            .map(|(tail, _)| *tail)
+            .collect();
+        if pending.is_empty() {
+            return Ok(vec![1.0; tails.len()]);
+        }
+
+        let selections = (0..CONFIDENCE_SAMPLES)
+            .map(|_| Client::get_transactions_to_approve().fresh(true).send());
+        let mut tips = Vec::with_capacity(CONFIDENCE_SAMPLES * 2);
+        for res in join_all(selections).await {
+            let res = res?;
+            tips.push(res.trunk_transaction);
+            tips.push(res.branch_transaction);
+        }
+
+        let pending = &pending;
+        let checks = tips.iter().map(|tip| async move {
+            Client::get_inclusion_states()
+                .transactions(pending)
+                .tips(std::slice::from_ref(tip))
+                .send()
+                .await
+        });
+        let mut references = vec![0usize; pending.len()];
+        for res in join_all(checks).await {
+            for (count, referenced) in references.iter_mut().zip(res?.states) {
+                if referenced {
+                    *count += 1;
+                }
+            }
+        }
+
+        let mut references = references.into_iter();
+        Ok(confirmed
+            .iter()
+            .map(|confirmed| {
+                if *confirmed {
+                    1.0
+                } else {
+                    references.next().unwrap_or(0) as f64 / tips.len() as f64
+                }
+            })
+            .collect())
+    }
+
     /// Gets latest solid subtangle milestone.
     pub async fn get_latest_solid_subtangle_milestone() -> Result<Hash> {
-        Ok(Hash::from_inner_unchecked(
-            // TODO missing impl error on Hash
-            TryteBuf::try_from_str(
-                &Client::get_node_info()
-                    .await?
-                    .latest_solid_subtangle_milestone,
-            )
-            .unwrap()
-            .as_trits()
-            .encode(),
-        ))
+        Ok(Client::get_latest_solid_milestone().await?.hash)
+    }
+
+    /// Gets hash and index of the latest solid subtangle milestone from GetNodeInfo.
+    pub async fn get_latest_solid_milestone() -> Result<Milestone> {
+        Client::get_node_info().await?.latest_solid_milestone()
+    }
+
+    /// Gets hash and index of the latest milestone from GetNodeInfo.
+    pub async fn get_latest_milestone() -> Result<Milestone> {
+        Client::get_node_info().await?.latest_milestone()
     }
 
     /// Gets all transaction hashes that a node is currently requesting from its neighbors.
@@ -330,7 +1189,8 @@ impl Client {
         GetNewAddressBuilder::new(seed)
     }
 
-    /// Gets a node's API configuration settings.
+    /// Gets a node's API configuration settings, like the maximum number of hashes of getTrytes
+    /// and the minimum weight magnitude of the network.
     pub async fn get_node_api_configuration() -> Result<GetNodeAPIConfigurationResponse> {
         let client = Client::get();
         let body = json!( {
@@ -369,34 +1229,140 @@ impl Client {
     /// Gets two consistent tip transaction hashes to use as branch/trunk transactions.
     /// This endpoint returns data only if the node is synchronized.
     /// # Parameters
-    /// * [`depth`] - Number of milestones to go back to start the tip selection algorithm. Default is 3.
+    /// * [`depth`] - Number of milestones to go back to start the tip selection algorithm, or [`Depth::Auto`].
     /// * [`reference`] - (Optional) Transaction hash from which to start the weighted random walk.
-    /// Use this parameter to make sure the returned tip transaction hashes approve a given reference transaction
+    ///   Use this parameter to make sure the returned tip transaction hashes approve a given reference transaction
+    /// * [`fresh`] - (Optional) Request new tips even if the tips of the last request are cached, see [`set_tips_cache_ttl`].
     ///
     /// [`depth`]: ../core/struct.GetTransactionsToApproveBuilder.html#method.depth
     /// [`reference`]: ../core/struct.GetTransactionsToApproveBuilder.html#method.reference
+    /// [`fresh`]: ../core/struct.GetTransactionsToApproveBuilder.html#method.fresh
+    /// [`set_tips_cache_ttl`]: #method.set_tips_cache_ttl
+    /// [`Depth::Auto`]: enum.Depth.html#variant.Auto
     pub fn get_transactions_to_approve() -> GetTransactionsToApproveBuilder {
         GetTransactionsToApproveBuilder::new()
     }
 
+    /// Fetches all bundles sent from or to the addresses of a seed, sorted by the attachment timestamp of
+    /// their tails. Addresses are generated from the start index until the first unused address without
+    /// transactions is detected. This stops working after a snapshot.
+    /// # Parameters
+    /// * [`seed`] - An iota seed.
+    /// * [`index`] - (Optional) Key index to start search at. Default is 0.
+    /// * [`security`] - (Optional) Security level. Default is 2.
+    /// * [`query`] - (Optional) Page of the sorted transfers to return. Default is all transfers.
+    ///
+    /// [`seed`]: ../extended/struct.GetTransfersBuilder.html#method.seed
+    /// [`index`]: ../extended/struct.GetTransfersBuilder.html#method.index
+    /// [`security`]: ../extended/struct.GetTransfersBuilder.html#method.security
+    /// [`query`]: ../extended/struct.GetTransfersBuilder.html#method.query
+    pub fn get_transfers(seed: &IotaSeed<Kerl>) -> GetTransfersBuilder<'_> {
+        GetTransfersBuilder::new(seed)
+    }
+
     /// Gets a transaction's contents in trytes.
+    /// Transactions the node doesn't know are returned as `None`, after asking as many other nodes of the
+    /// pool as set with [`set_trytes_retries`].
+    /// Large lists are split into requests of at most the node's `maxGetTrytes` hashes, which are sent concurrently.
     /// # Parameters
     /// * `hashes` - Transaction hashes
     ///
     /// [`hashes`]: ../core/struct.GetTrytesBuilder.html#method.hashes
+    /// [`set_trytes_retries`]: #method.set_trytes_retries
     pub async fn get_trytes(hashes: &[Hash]) -> Result<GetTrytesResponse> {
-        let hashes: Vec<String> = hashes
+        let client = Client::get();
+        let node = Client::get_node()?;
+        let mut trytes = client.get_trytes_from(node.clone(), hashes).await?;
+
+        let retries = *client
+            .trytes_retries
+            .read()
+            .map_err(|_| anyhow!("Trytes retries read poisened"))?;
+        let others: Vec<Url> = client
+            .pool
+            .read()
+            .map_err(|_| anyhow!("Node pool read poinsened"))?
             .iter()
-            .map(|h| h.as_bytes().trytes().unwrap())
+            .filter(|url| **url != node)
+            .take(retries)
+            .cloned()
             .collect();
-        let client = Client::get();
-        let body = json!({
-            "command": "getTrytes",
-            "hashes": hashes,
+        for other in others {
+            let missing: Vec<usize> = (0..trytes.len()).filter(|i| trytes[*i].is_none()).collect();
+            if missing.is_empty() {
+                break;
+            }
+            let missing_hashes: Vec<Hash> = missing.iter().map(|i| hashes[*i]).collect();
+            // A failing node only means one retry less
+            if let Ok(found) = client.get_trytes_from(other, &missing_hashes).await {
+                for (i, tx) in missing.into_iter().zip(found) {
+                    trytes[i] = tx;
+                }
+            }
+        }
+
+        Ok(GetTrytesResponse { trytes })
+    }
+
+    /// Streams the transactions of the given hashes in their order, so large lists can be processed
+    /// without holding all transactions in memory. The hashes are requested in chunks of the node's
+    /// `maxGetTrytes`, with at most [`MAX_CONCURRENT_GET_TRYTES`] requests at a time, and the transactions
+    /// of a chunk are yielded as soon as it and all chunks before it completed.
+    /// Unlike [`get_trytes`], transactions the node doesn't know are not asked from other nodes.
+    /// # Parameters
+    /// * `hashes` - Transaction hashes
+    ///
+    /// [`MAX_CONCURRENT_GET_TRYTES`]: constant.MAX_CONCURRENT_GET_TRYTES.html
+    /// [`get_trytes`]: #method.get_trytes
+    pub fn get_trytes_stream(hashes: Vec<Hash>) -> impl Stream<Item = Result<Option<Transaction>>> {
+        stream::once(async move {
+            let client = Client::get();
+            let node = Client::get_node()?;
+            let max = client.api_limits(&node).await?.max_get_trytes;
+            let chunks: Vec<Vec<Hash>> = hashes.chunks(max).map(<[Hash]>::to_vec).collect();
+            Ok::<_, anyhow::Error>(
+                stream::iter(chunks)
+                    .map(move |chunk| {
+                        let node = node.clone();
+                        async move { client.get_trytes_from(node, &chunk).await }
+                    })
+                    .buffered(MAX_CONCURRENT_GET_TRYTES),
+            )
+        })
+        .try_flatten()
+        .map_ok(|trytes| stream::iter(trytes.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    // Sends getTrytes to the given node, in chunks of its maxGetTrytes.
+    async fn get_trytes_from(
+        &self,
+        node: Url,
+        hashes: &[Hash],
+    ) -> Result<Vec<Option<Transaction>>> {
+        let max = self.api_limits(&node).await?.max_get_trytes;
+        let requests = hashes.chunks(max).map(|chunk| {
+            let hashes: Vec<String> = chunk
+                .iter()
+                .map(|h| h.as_bytes().trytes().unwrap())
+                .collect();
+            let body = json!({
+                "command": "getTrytes",
+                "hashes": hashes,
+            });
+            let node = node.clone();
+
+            async move {
+                let res: GetTrytesResponseBuilder = response!(self, body, node);
+                res.build().await
+            }
         });
 
-        let res: GetTrytesResponseBuilder = response!(client, body);
-        res.build().await
+        let mut trytes = Vec::with_capacity(hashes.len());
+        for res in join_all(requests).await {
+            trytes.extend(res?.trytes);
+        }
+        Ok(trytes)
     }
 
     /// Aborts the process that's started by the `attach_to_tangle` method.
@@ -405,14 +1371,7 @@ impl Client {
             "command": "interruptAttachingToTangle",
         });
 
-        let _ = Client::get()
-            .client
-            .post(Client::get_node()?)
-            .header("Content-Type", "application/json")
-            .header("X-IOTA-API-Version", "1")
-            .body(body.to_string())
-            .send()
-            .await?;
+        let _ = Client::get().send(Client::get_node()?, &body).await?;
 
         Ok(())
     }
@@ -421,8 +1380,9 @@ impl Client {
     /// # Parameters
     /// * `address` - IOTA address
     pub async fn is_address_used(address: &Address) -> Result<bool> {
-        let addresses = &[address.clone()];
-        let spent = Client::were_addresses_spent_from(addresses).await?.states[0];
+        let spent = Client::were_addresses_spent_from(std::slice::from_ref(address))
+            .await?
+            .states[0];
 
         // TODO more address evaluations
         if spent {
@@ -432,6 +1392,49 @@ impl Client {
         }
     }
 
+    /// Gets the balance and spent state of addresses with getBalances and wereAddressesSpentFrom,
+    /// which are sent concurrently, so scanning addresses takes a single round trip.
+    /// Large lists are split into requests of at most [`MAX_ADDRESSES_PER_REQUEST`] addresses for
+    /// both commands, which are sent concurrently as well. Fails if the node doesn't return a balance
+    /// and a spent state for every address.
+    /// # Parameters
+    /// * `addresses` - addresses to check (do not include the checksum)
+    ///
+    /// [`MAX_ADDRESSES_PER_REQUEST`]: constant.MAX_ADDRESSES_PER_REQUEST.html
+    pub async fn get_address_states(addresses: &[Address]) -> Result<Vec<AddressState>> {
+        let balances = async {
+            let requests = addresses
+                .chunks(MAX_ADDRESSES_PER_REQUEST)
+                .map(|chunk| Client::get_balances().addresses(chunk).send());
+            let mut balances = Vec::with_capacity(addresses.len());
+            for res in join_all(requests).await {
+                balances.extend(res?.balances);
+            }
+            Ok::<_, anyhow::Error>(balances)
+        };
+        let (balances, spent) =
+            futures::try_join!(balances, Client::were_addresses_spent_from(addresses))?;
+        if balances.len() != addresses.len() || spent.states.len() != addresses.len() {
+            return Err(anyhow!(
+                "Node returned {} balances and {} spent states for {} addresses",
+                balances.len(),
+                spent.states.len(),
+                addresses.len()
+            ));
+        }
+
+        Ok(addresses
+            .iter()
+            .zip(balances)
+            .zip(spent.states)
+            .map(|((address, balance), spent)| AddressState {
+                address: address.clone(),
+                balance,
+                spent,
+            })
+            .collect())
+    }
+
     /// checks if a transaction is promotable by calling the checkConsistency IRI API command and
     /// verifying that attachmentTimestamp is above a lower bound. Lower bound is calculated based on the number of milestones issued
     /// since transaction attachment.
@@ -441,12 +1444,14 @@ impl Client {
         let is_consistent = Client::check_consistency(&[*tail]).await?.state;
 
         let timestamp = *Client::get_trytes(&[*tail]).await?.trytes[0]
+            .as_ref()
+            .ok_or_else(|| anyhow!("Tail transaction not found"))?
             .attachment_ts()
             .to_inner() as i64;
 
         let now = chrono::Utc::now().timestamp();
         let milestone_interval: i64 = 2 * 60 * 1000;
-        let one_way_delay: i64 = 1 * 60 * 1000;
+        let one_way_delay: i64 = 60 * 1000;
         let max_depth: i64 = 6;
 
         let is_above_max_depth =
@@ -482,7 +1487,7 @@ impl Client {
     /// [`uris`]: ../core/struct.RemoveNeighborsBuilder.html#method.uris
     pub async fn remove_neighbors(uris: Vec<String>) -> Result<RemoveNeighborsResponse> {
         for uri in &uris {
-            match Url::parse(&uri)?.scheme() {
+            match Url::parse(uri)?.scheme() {
                 "tcp" | "udp" => (),
                 _ => return Err(anyhow!("Uri scheme should be either tcp or udp")),
             }
@@ -509,18 +1514,22 @@ impl Client {
     pub async fn replay_bundle(hash: &Hash) -> Result<SendTrytesBuilder> {
         let mut bundle = Client::get_bundle(hash).await?;
         bundle.reverse();
-        Ok(SendTrytesBuilder::new().trytes(bundle))
+        Ok(SendTrytesBuilder::new().trytes(bundle).reattach())
     }
 
     /// Calls PrepareTransfers and then sends off the bundle via SendTrytes.
+    /// All transfers go into a single bundle, sharing the inputs and the remainder, so they are
+    /// confirmed together or not at all.
     /// * [`seed`] - An iota seed.
     /// * [`transfers`] - Transfer addresses to send data/value to.
     /// * [`inputs`] - (Optional, but recommended) Input addresses used for signing. Use `get_inputs` to get the valid inputs yourself.
     /// * [`remainder`] - (Optional) Custom remainder address.
     /// * [`security`] - (Optional) Security level. Default is 2.
-    /// * [`depth`] - Number of milestones to go back to start the tip selection algorithm. Default is 3.
+    /// * [`depth`] - (Optional) Number of milestones to go back to start the tip selection algorithm.
+    ///   Default is [`Depth::Auto`].
     /// * [`min_weight_magnitude`] - Difficulty of PoW
     /// * [`reference`] - (Optional) Transaction hash from which to start the weighted random walk.
+    /// * [`tip_retries`] - (Optional) How many times inconsistent tips are requested again. Default is 3.
     ///
     /// [`seed`]: ../extended/struct.SendTransfersBuilder.html#method.seed
     /// [`transfers`]: ../extended/struct.SendTransfersBuilder.html#method.transfers
@@ -531,21 +1540,34 @@ impl Client {
     /// [`depth`]: ../extended/struct.SendTransfersBuilder.html#method.depth
     /// [`min_weight_magnitude`]: ../extended/struct.SendTransfersBuilder.html#method.min_weight_magnitude
     /// [`reference`]: ../extended/struct.SendTransfersBuilder.html#method.reference
+    /// [`tip_retries`]: ../extended/struct.SendTransfersBuilder.html#method.tip_retries
+    /// [`Depth::Auto`]: enum.Depth.html#variant.Auto
     pub fn send_transfers(seed: &IotaSeed<Kerl>) -> SendTransfersBuilder<'_> {
         SendTransfersBuilder::new(seed)
     }
 
     /// Perform Attaches to tanlge, stores and broadcasts a vector of transaction trytes.
+    /// A bundle which has already been stored and broadcast by this client is not sent again, its
+    /// attached transactions are returned instead, so retrying after a network error can't send it
+    /// twice. Sending a bundle while another call is still sending it fails with
+    /// [`Error::BundleInFlight`]. Reattachments made with [`replay_bundle`] are always sent. Only
+    /// the last [`MAX_SENT_BUNDLES`] bundles are remembered, see [`set_max_sent_bundles`].
     /// # Parameters
     /// * [`trytes`] - Vector of trytes to attach, store & broadcast
     /// * [`depth`] - Number of milestones to go back to start the tip selection algorithm. Default is 3.
     /// * [`min_weight_magnitude`] - Difficulty of PoW
     /// * [`reference`] - (Optional) Transaction hash from which to start the weighted random walk.
+    /// * [`tip_retries`] - (Optional) How many times inconsistent tips are requested again. Default is 3.
     ///
     /// [`trytes`]: ../extended/struct.SendTrytesBuilder.html#method.trytes
     /// [`depth`]: ../extended/struct.SendTrytesBuilder.html#method.depth
     /// [`min_weight_magnitude`]: ../extended/struct.SendTrytesBuilder.html#method.min_weight_magnitude
     /// [`reference`]: ../extended/struct.SendTrytesBuilder.html#method.reference
+    /// [`tip_retries`]: ../extended/struct.SendTrytesBuilder.html#method.tip_retries
+    /// [`replay_bundle`]: #method.replay_bundle
+    /// [`Error::BundleInFlight`]: ../error/enum.Error.html#variant.BundleInFlight
+    /// [`MAX_SENT_BUNDLES`]: constant.MAX_SENT_BUNDLES.html
+    /// [`set_max_sent_bundles`]: #method.set_max_sent_bundles
     pub fn send_trytes() -> SendTrytesBuilder {
         SendTrytesBuilder::new()
     }
@@ -570,7 +1592,7 @@ impl Client {
     /// [`trytes`]: ../core/struct.StoreTransactionsBuilder.html#method.trytes
     pub async fn store_transactions(trytes: &[Transaction]) -> Result<()> {
         let client = Client::get();
-        let trytes: Vec<String> = trytes.iter().map(|tx| tx_trytes(tx)).collect();
+        let trytes: Vec<String> = trytes.iter().map(tx_trytes).collect();
         let body = json!({
             "command": "storeTransactions",
             "trytes": trytes,
@@ -582,26 +1604,63 @@ impl Client {
 
     /// Fetches the bundle of a given the tail transaction hash, by traversing through trunk transaction.
     /// It does not validate the bundle. Use [`get_bundle`] instead to get validated bundle.
+    /// Bundles longer than set with [`set_max_bundle_length`] fail with [`Error::BundleTooLong`],
+    /// trunks leading back to a transaction which has already been fetched fail with
+    /// [`Error::BundleCycleDetected`].
     ///
     /// # Parameters
     /// * [`hash`] - Tail transaction hash (current_index == 0)
     ///
     /// [`get_bundle`]: #method.get_bundle
+    /// [`set_max_bundle_length`]: #method.set_max_bundle_length
+    /// [`Error::BundleTooLong`]: ../error/enum.Error.html#variant.BundleTooLong
+    /// [`Error::BundleCycleDetected`]: ../error/enum.Error.html#variant.BundleCycleDetected
     pub async fn traverse_bundle(hash: &Hash) -> Result<Vec<Transaction>> {
-        let mut bundle = Vec::new();
+        let max = *Client::get()
+            .max_bundle_length
+            .read()
+            .map_err(|_| anyhow!("Max bundle length read poisened"))?;
+        let mut bundle: Vec<Transaction> = Vec::new();
+        let mut visited = HashSet::new();
         let mut hash = *hash;
-        let mut tail = true;
         loop {
-            let res = Client::get_trytes(&[hash]).await?.trytes.pop().unwrap();
+            visited.insert(hash);
+            let res = Client::get_trytes(&[hash])
+                .await?
+                .trytes
+                .pop()
+                .flatten()
+                .ok_or_else(|| anyhow!("Transaction not found while traversing the bundle"))?;
+            // Only the head's trunk leaves the bundle, every other trunk is followed
+            if res.index() != res.last_index() && visited.contains(res.trunk()) {
+                break Err(Error::BundleCycleDetected.into());
+            }
+            if transaction_hash(&res) != hash {
+                break Err(anyhow!("Node returned a transaction with a different hash"));
+            }
 
-            if tail {
-                if *res.index().to_inner() != 0 {
-                    break Err(anyhow!("Provided hash is not tail."));
+            match bundle.first() {
+                None => {
+                    if *res.index().to_inner() != 0 {
+                        break Err(anyhow!("Provided hash is not tail."));
+                    }
+                    if *res.last_index().to_inner() >= max {
+                        break Err(Error::BundleTooLong(max).into());
+                    }
+                }
+                Some(tail) => {
+                    if *res.index().to_inner() != bundle.len()
+                        || res.last_index() != tail.last_index()
+                    {
+                        break Err(anyhow!(
+                            "Transaction {} of the bundle has an inconsistent index.",
+                            bundle.len()
+                        ));
+                    }
                 }
-                tail = false;
             }
 
-            hash = res.trunk().clone();
+            hash = *res.trunk();
             if res.index() == res.last_index() {
                 bundle.push(res);
                 break Ok(bundle);
@@ -613,22 +1672,83 @@ impl Client {
 
     /// Checks if an address was ever withdrawn from, either in the current epoch or in any previous epochs.
     /// If an address has a pending transaction, it's also considered 'spent'.
+    /// Large lists are split into requests of at most [`MAX_ADDRESSES_PER_REQUEST`] addresses, which are sent concurrently.
+    /// Spending is permanent, so addresses a node once reported as spent are remembered and not requested again.
+    /// Nodes which list their features without `WereAddressesSpentFrom` make this fail with
+    /// [`Error::UnsupportedCommand`] without sending the request.
     /// # Parameters
     /// * `address` - addresses to check (do not include the checksum)
+    ///
+    /// [`MAX_ADDRESSES_PER_REQUEST`]: constant.MAX_ADDRESSES_PER_REQUEST.html
+    /// [`Error::UnsupportedCommand`]: ../error/enum.Error.html#variant.UnsupportedCommand
     pub async fn were_addresses_spent_from(
         addresses: &[Address],
     ) -> Result<WereAddressesSpentFromResponse> {
+        let client = Client::get();
         let addresses: Vec<String> = addresses
             .iter()
             .map(|h| h.to_inner().as_i8_slice().trytes().unwrap())
             .collect();
-        let client = Client::get();
-        let body = json!({
-            "command": "wereAddressesSpentFrom",
-            "addresses": addresses,
+        let mut states: Vec<bool> = {
+            let spent = client
+                .spent_addresses
+                .read()
+                .map_err(|_| anyhow!("Spent addresses read poisened"))?;
+            addresses.iter().map(|a| spent.contains(a)).collect()
+        };
+        let unknown: Vec<&String> = addresses
+            .iter()
+            .zip(&states)
+            .filter(|(_, spent)| !**spent)
+            .map(|(address, _)| address)
+            .collect();
+
+        if unknown.is_empty() {
+            return Ok(WereAddressesSpentFromResponse { states });
+        }
+        let node = Client::get_node()?;
+        client
+            .require_command(&node, "wereAddressesSpentFrom")
+            .await?;
+
+        let requests = unknown.chunks(MAX_ADDRESSES_PER_REQUEST).map(|chunk| {
+            let body = json!({
+                "command": "wereAddressesSpentFrom",
+                "addresses": chunk,
+            });
+            let node = node.clone();
+
+            async move {
+                let res: WereAddressesSpentFromResponseBuilder = response!(client, body, node);
+                res.build().await
+            }
         });
 
-        let res: WereAddressesSpentFromResponseBuilder = response!(client, body);
-        res.build().await
+        let mut unknown_states = Vec::with_capacity(unknown.len());
+        for res in join_all(requests).await {
+            unknown_states.extend(res?.states);
+        }
+        if unknown_states.len() != unknown.len() {
+            return Err(anyhow!(
+                "Node returned {} states for {} addresses",
+                unknown_states.len(),
+                unknown.len()
+            ));
+        }
+
+        let mut spent = client
+            .spent_addresses
+            .write()
+            .map_err(|_| anyhow!("Spent addresses write poisened"))?;
+        let mut unknown_states = unknown_states.into_iter();
+        for (address, state) in addresses.iter().zip(states.iter_mut()) {
+            if !*state {
+                *state = unknown_states.next() == Some(true);
+                if *state {
+                    spent.insert(address.clone());
+                }
+            }
+        }
+        Ok(WereAddressesSpentFromResponse { states })
     }
 }
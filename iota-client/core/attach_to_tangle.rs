@@ -46,11 +46,14 @@ impl AttachToTangleBuilder {
     /// Add slice of transaction trytes. When sending transactions in a bundle,
     /// make sure that the trytes of the last transaction in the bundle are in index 0 of the array.
     pub fn trytes(mut self, trytes: &[Transaction]) -> Self {
-        self.trytes = trytes.iter().map(|tx| tx_trytes(tx)).collect();
+        self.trytes = trytes.iter().map(tx_trytes).collect();
         self
     }
 
-    /// Send attachToTangle request
+    /// Send attachToTangle request. Nodes which list their features without `RemotePOW` make this
+    /// fail with [`Error::UnsupportedCommand`] without sending the request.
+    ///
+    /// [`Error::UnsupportedCommand`]: ../error/enum.Error.html#variant.UnsupportedCommand
     pub async fn send(self) -> Result<AttachToTangleResponse> {
         let client = Client::get();
         let body = json!({
@@ -61,7 +64,9 @@ impl AttachToTangleBuilder {
             "trytes": self.trytes,
         });
 
-        let res: AttachToTangleResponseBuilder = response!(client, body);
+        let node = Client::get_node()?;
+        client.require_command(&node, "attachToTangle").await?;
+        let res: AttachToTangleResponseBuilder = response!(client, body, node);
         res.build().await
     }
 }
@@ -1,4 +1,7 @@
+use std::collections::HashSet;
+
 use anyhow::Result;
+use futures::future::join_all;
 use iota_bundle_preview::{Address, Hash, Tag, TransactionField};
 use iota_conversion::Trinary;
 
@@ -12,6 +15,7 @@ pub struct FindTransactionsBuilder {
     addresses: Option<Vec<String>>,
     tags: Option<Vec<String>>,
     approvees: Option<Vec<String>>,
+    sorted: bool,
 }
 
 impl FindTransactionsBuilder {
@@ -21,6 +25,7 @@ impl FindTransactionsBuilder {
             addresses: Default::default(),
             tags: Default::default(),
             approvees: Default::default(),
+            sorted: false,
         }
     }
 
@@ -67,9 +72,20 @@ impl FindTransactionsBuilder {
         self
     }
 
-    /// Send findTransactions request
+    /// Sort the hashes by their trytes, so the same query always returns the same order.
+    /// Nodes return them in an unspecified order otherwise. Default is false.
+    pub fn sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
+    }
+
+    /// Send findTransactions request. Every list longer than the node's `maxRequestsList` is split
+    /// into chunks of at most that many values, and a request is sent for each combination of the
+    /// chunks. The requests are sent concurrently.
     pub async fn send(self) -> Result<FindTransactionsResponse> {
         let client = Client::get();
+        let node = Client::get_node()?;
+        let max = client.api_limits(&node).await?.max_requests_list;
         let mut body = json!({
             "command": "findTransactions",
         });
@@ -90,7 +106,46 @@ impl FindTransactionsBuilder {
             body["approvees"] = json!(approvees);
         }
 
-        let res: FindTransactionsResponseBuilder = response!(client, body);
-        res.build().await
+        // Nodes return the transactions matching all given fields, so querying every combination
+        // of the chunks and joining the results doesn't change the result.
+        let mut bodies = vec![body];
+        for field in &["bundles", "addresses", "tags", "approvees"] {
+            let values = match bodies[0][*field].as_array() {
+                Some(values) if values.len() > max => values.clone(),
+                _ => continue,
+            };
+            bodies = bodies
+                .into_iter()
+                .flat_map(|body| {
+                    values.chunks(max).map(move |chunk| {
+                        let mut body = body.clone();
+                        body[*field] = json!(chunk);
+                        body
+                    })
+                })
+                .collect();
+        }
+
+        let requests = bodies.into_iter().map(|body| {
+            let node = node.clone();
+            async move {
+                let res: FindTransactionsResponseBuilder = response!(client, body, node);
+                res.build().await
+            }
+        });
+
+        let mut seen = HashSet::new();
+        let mut hashes = Vec::new();
+        for res in join_all(requests).await {
+            for hash in res?.hashes {
+                if seen.insert(hash) {
+                    hashes.push(hash);
+                }
+            }
+        }
+        if self.sorted {
+            hashes.sort_by_cached_key(|hash| hash.as_bytes().trytes().unwrap());
+        }
+        Ok(FindTransactionsResponse { hashes })
     }
 }
@@ -31,7 +31,8 @@ impl GetBalancesBuilder {
         self
     }
 
-    /// Set confirmation threshold between 0 and 100
+    /// Set confirmation threshold between 0 and 100, default is 100. It is the percentage of
+    /// the node's tips which have to approve a transaction for its value to count towards the balance.
     pub fn threshold(mut self, threshold: u8) -> Self {
         self.threshold = threshold;
         self
@@ -49,6 +50,22 @@ impl GetBalancesBuilder {
 
     /// Send getBalances request
     pub async fn send(self) -> Result<GetBalancesResponse> {
+        if self.threshold > 100 {
+            return Err(anyhow!(
+                "Threshold {} is not between 0 and 100",
+                self.threshold
+            ));
+        }
+
+        // There is nothing to ask the node about
+        if self.addresses.is_empty() {
+            return Ok(GetBalancesResponse {
+                balances: Vec::new(),
+                milestone_index: 0,
+                references: Vec::new(),
+            });
+        }
+
         let client = Client::get();
         let mut body = json!({
             "command": "getBalances",
@@ -1,28 +1,32 @@
 use anyhow::Result;
+use chrono::Utc;
 use iota_bundle_preview::Hash;
 use iota_conversion::Trinary;
 
+use crate::client::{CachedTips, Depth};
 use crate::response::{GTTAResponse, GTTAResponseBuilder};
 use crate::Client;
 
 /// Builder to construct getTransactionsToApprove API
 #[derive(Debug)]
 pub struct GetTransactionsToApproveBuilder {
-    depth: u8,
+    depth: Depth,
     reference: Option<String>,
+    fresh: bool,
 }
 
 impl GetTransactionsToApproveBuilder {
     pub(crate) fn new() -> Self {
         Self {
-            depth: Default::default(),
+            depth: Depth::Fixed(0),
             reference: Default::default(),
+            fresh: false,
         }
     }
 
-    /// The depth for the random walk in the tip selection
-    pub fn depth(mut self, depth: u8) -> Self {
-        self.depth = depth;
+    /// The depth for the random walk in the tip selection, a number of milestones or `Depth::Auto`
+    pub fn depth(mut self, depth: impl Into<Depth>) -> Self {
+        self.depth = depth.into();
         self
     }
 
@@ -32,19 +36,61 @@ impl GetTransactionsToApproveBuilder {
         self
     }
 
+    /// Request new tips even if cached tips are still valid
+    pub fn fresh(mut self, fresh: bool) -> Self {
+        self.fresh = fresh;
+        self
+    }
+
     /// Send getTransactionsToApprove request
     pub async fn send(self) -> Result<GTTAResponse> {
         let client = Client::get();
         let mut body = json!({
             "command": "getTransactionsToApprove",
-            "depth": self.depth,
+            "depth": self.depth.resolve().await?,
         });
 
         if let Some(reference) = self.reference {
             body["reference"] = json!(reference);
         }
 
+        let ttl = *client
+            .tips_cache_ttl
+            .read()
+            .map_err(|_| anyhow!("Tips cache TTL read poisened"))?;
+        let ttl = match chrono::Duration::from_std(ttl) {
+            Ok(ttl) if ttl > chrono::Duration::zero() => ttl,
+            _ => {
+                let res: GTTAResponseBuilder = response!(client, body);
+                return res.build().await;
+            }
+        };
+
+        if !self.fresh {
+            if let Some(cached) = client
+                .tips_cache
+                .read()
+                .map_err(|_| anyhow!("Tips cache read poisened"))?
+                .as_ref()
+            {
+                if cached.body == body && cached.expires > Utc::now() {
+                    return Ok(cached.tips.clone());
+                }
+            }
+        }
+
         let res: GTTAResponseBuilder = response!(client, body);
-        res.build().await
+        let tips = res.build().await?;
+        if let Some(expires) = Utc::now().checked_add_signed(ttl) {
+            *client
+                .tips_cache
+                .write()
+                .map_err(|_| anyhow!("Tips cache write poisened"))? = Some(CachedTips {
+                body,
+                tips: tips.clone(),
+                expires,
+            });
+        }
+        Ok(tips)
     }
 }
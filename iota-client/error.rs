@@ -0,0 +1,93 @@
+//! Error types
+use std::fmt;
+
+/// Errors of the client which callers might want to handle explicitly.
+/// They are returned wrapped in `anyhow::Error`, use `downcast_ref::<Error>()` to inspect them.
+#[derive(Debug)]
+pub enum Error {
+    /// The node doesn't support the given API command
+    UnsupportedCommand(String),
+    /// The bundle has more transactions than the given maximum
+    BundleTooLong(usize),
+    /// The trunk chain of a bundle leads back to a transaction it already went through
+    BundleCycleDetected,
+    /// Another `send_trytes` call is sending the bundle of the given hash right now
+    BundleInFlight(String),
+    /// The transfer value, or the sum of all transfer values, exceeds the total supply
+    InvalidTransferValue(u64),
+    /// The sum of the values of a bundle doesn't fit into an i64
+    ValueOverflow,
+    /// The node returned no consistent tips in the given number of attempts
+    CouldNotGetConsistentTips(usize),
+    /// The node rejected the X-IOTA-API-Version header, which had the given value
+    MissingApiVersion(String),
+    /// The response of a node couldn't be parsed, e.g. because a proxy answered with an HTML page
+    Deserialization {
+        /// Command of the request
+        command: String,
+        /// Start of the response
+        snippet: String,
+    },
+    /// The inputs hold less balance than the transfers need
+    InsufficientBalance {
+        /// Sum of the transfer values
+        needed: u64,
+        /// Balance of the inputs
+        available: u64,
+    },
+    /// An output is below the dust threshold and goes to an address holding less than it
+    DustOutput {
+        /// Address of the output
+        address: String,
+        /// Value of the output
+        value: u64,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedCommand(command) => {
+                write!(f, "Node doesn't support the {} command", command)
+            }
+            Error::BundleTooLong(max) => {
+                write!(f, "Bundle has more than {} transactions", max)
+            }
+            Error::BundleCycleDetected => {
+                write!(f, "Trunk chain of the bundle leads back into itself")
+            }
+            Error::BundleInFlight(bundle) => {
+                write!(f, "Bundle {} is being sent already", bundle)
+            }
+            Error::InvalidTransferValue(value) => {
+                write!(f, "Transfer value {} exceeds the total supply", value)
+            }
+            Error::ValueOverflow => write!(f, "Sum of the bundle values overflows"),
+            Error::CouldNotGetConsistentTips(attempts) => {
+                write!(f, "Could not get consistent tips in {} attempts", attempts)
+            }
+            Error::MissingApiVersion(version) => write!(
+                f,
+                "Node rejected API version {}, set a version it supports with Client::set_api_version",
+                version
+            ),
+            Error::Deserialization { command, snippet } => write!(
+                f,
+                "Could not parse the response to {}: {}",
+                command, snippet
+            ),
+            Error::InsufficientBalance { needed, available } => write!(
+                f,
+                "Insufficient balance: needed {}, available {}",
+                needed, available
+            ),
+            Error::DustOutput { address, value } => write!(
+                f,
+                "Output of {} to {} is dust, as the address holds less than the dust threshold",
+                value, address
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -2,6 +2,7 @@ use anyhow::Result;
 use iota_crypto_preview::Kerl;
 use iota_signing_preview::IotaSeed;
 
+use crate::error::Error;
 use crate::response::Input;
 use crate::Client;
 
@@ -17,7 +18,7 @@ pub struct GetInputsBuilder<'a> {
 impl<'a> GetInputsBuilder<'a> {
     pub(crate) fn new(seed: &'a IotaSeed<Kerl>) -> Self {
         Self {
-            seed: seed,
+            seed,
             index: 0,
             security: 2,
             threshold: 0,
@@ -42,7 +43,10 @@ impl<'a> GetInputsBuilder<'a> {
         self
     }
 
-    /// Send GetInputs request
+    /// Send GetInputs request. Fails with [`Error::InsufficientBalance`] if five addresses in a row
+    /// have no balance before the threshold is reached.
+    ///
+    /// [`Error::InsufficientBalance`]: ../error/enum.Error.html#variant.InsufficientBalance
     pub async fn generate(self) -> Result<(u64, Vec<Input>)> {
         if self.threshold == 0 {
             return Ok((0, Vec::default()));
@@ -61,7 +65,7 @@ impl<'a> GetInputsBuilder<'a> {
                 .await?;
 
             let balance = Client::get_balances()
-                .addresses(&[address.clone()])
+                .addresses(std::slice::from_ref(&address))
                 .send()
                 .await?
                 .balances[0];
@@ -87,6 +91,10 @@ impl<'a> GetInputsBuilder<'a> {
             }
         }
 
-        Err(anyhow!("Cannot find enough inputs to satisify threshold"))
+        Err(Error::InsufficientBalance {
+            needed: self.threshold,
+            available: total,
+        }
+        .into())
     }
 }
@@ -6,6 +6,7 @@ use iota_signing_preview::{
     WotsSecurityLevel,
 };
 
+use crate::util::add_checksum;
 use crate::Client;
 
 /// Builder to construct GetNewAddress API
@@ -19,7 +20,7 @@ pub struct GetNewAddressBuilder<'a> {
 impl<'a> GetNewAddressBuilder<'a> {
     pub(crate) fn new(seed: &'a IotaSeed<Kerl>) -> Self {
         Self {
-            seed: seed,
+            seed,
             index: 0,
             security: WotsSecurityLevel::Medium,
         }
@@ -61,11 +62,17 @@ impl<'a> GetNewAddressBuilder<'a> {
                     .to_owned(),
             );
 
-            if let Ok(false) = Client::is_address_used(&address).await {
+            if !Client::is_address_used(&address).await? {
                 break Ok((index, address));
             }
 
             index += 1;
         }
     }
+
+    /// Send GetNewAddress request and return the address with its checksum, as 90 trytes
+    pub async fn generate_with_checksum(self) -> Result<(u64, String)> {
+        let (index, address) = self.generate().await?;
+        Ok((index, add_checksum(&address)))
+    }
 }
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use iota_bundle_preview::{Address, Hash, Transaction, TransactionField};
+use iota_crypto_preview::Kerl;
+use iota_signing_preview::{
+    IotaSeed, PrivateKey, PrivateKeyGenerator, PublicKey, WotsPrivateKeyGeneratorBuilder,
+    WotsSecurityLevel,
+};
+
+use crate::Client;
+
+/// Page of the transfers returned by GetTransfers
+#[derive(Clone, Copy, Debug)]
+pub struct TransferQuery {
+    /// Number of transfers skipped, in the order of their tails' attachment timestamps
+    pub start: usize,
+    /// Maximum number of transfers returned
+    pub limit: usize,
+}
+
+impl Default for TransferQuery {
+    fn default() -> Self {
+        Self {
+            start: 0,
+            limit: usize::MAX,
+        }
+    }
+}
+
+/// Builder to construct GetTransfers API
+//#[derive(Debug)]
+pub struct GetTransfersBuilder<'a> {
+    seed: &'a IotaSeed<Kerl>,
+    index: u64,
+    security: WotsSecurityLevel,
+    query: TransferQuery,
+}
+
+impl<'a> GetTransfersBuilder<'a> {
+    pub(crate) fn new(seed: &'a IotaSeed<Kerl>) -> Self {
+        Self {
+            seed,
+            index: 0,
+            security: WotsSecurityLevel::Medium,
+            query: TransferQuery::default(),
+        }
+    }
+
+    /// Set key index to start search at
+    pub fn index(mut self, index: u64) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Set security level
+    pub fn security(mut self, security: u8) -> Self {
+        self.security = match security {
+            1 => WotsSecurityLevel::Low,
+            2 => WotsSecurityLevel::Medium,
+            3 => WotsSecurityLevel::High,
+            _ => panic!("Invalid security level"),
+        };
+        self
+    }
+
+    /// Set the page of transfers to return. Default is all transfers.
+    pub fn query(mut self, query: TransferQuery) -> Self {
+        self.query = query;
+        self
+    }
+
+    /// Send GetTransfers request
+    pub async fn send(self) -> Result<Vec<Vec<Transaction>>> {
+        let mut index = self.index;
+        let mut hashes = HashSet::new();
+
+        // Addresses are scanned until the first one without transactions which was never spent from.
+        loop {
+            // TODO impl Error trait in iota_signing_preview
+            let address = Address::from_inner_unchecked(
+                WotsPrivateKeyGeneratorBuilder::<Kerl>::default()
+                    .security_level(self.security)
+                    .build()
+                    .unwrap()
+                    .generate(self.seed, index)
+                    .unwrap()
+                    .generate_public_key()
+                    .unwrap()
+                    .trits()
+                    .to_owned(),
+            );
+
+            let found = Client::find_transactions()
+                .addresses(std::slice::from_ref(&address))
+                .send()
+                .await?
+                .hashes;
+
+            if found.is_empty() && !Client::is_address_used(&address).await? {
+                break;
+            }
+
+            hashes.extend(found);
+            index += 1;
+        }
+
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let hashes: Vec<Hash> = hashes.into_iter().collect();
+        let bundles: HashSet<Hash> = Client::get_trytes(&hashes)
+            .await?
+            .trytes
+            .into_iter()
+            .flatten()
+            .map(|tx| *tx.bundle())
+            .collect();
+
+        // Every tail of the bundles is reassembled, so reattachments show up as separate transfers.
+        let bundles: Vec<Hash> = bundles.into_iter().collect();
+        let hashes = Client::find_transactions()
+            .bundles(&bundles)
+            .send()
+            .await?
+            .hashes;
+        let transactions = Client::get_trytes(&hashes).await?.trytes;
+
+        // Tails are sorted before traversing, so only the bundles of the page are fetched.
+        let mut tails: Vec<(u64, Hash)> = hashes
+            .iter()
+            .zip(transactions)
+            .filter_map(|(hash, transaction)| match transaction {
+                Some(tx) if *tx.index().to_inner() == 0 => {
+                    Some((*tx.attachment_ts().to_inner(), *hash))
+                }
+                _ => None,
+            })
+            .collect();
+        tails.sort_by_key(|(attachment_ts, _)| *attachment_ts);
+
+        let mut transfers = Vec::new();
+        for (_, hash) in tails.iter().skip(self.query.start).take(self.query.limit) {
+            transfers.push(Client::traverse_bundle(hash).await?);
+        }
+        Ok(transfers)
+    }
+}
@@ -2,12 +2,14 @@
 
 mod get_inputs;
 mod get_new_address;
+mod get_transfers;
 mod prepare_transfers;
 mod send_transfers;
 mod send_trytes;
 
 pub use get_inputs::GetInputsBuilder;
 pub use get_new_address::GetNewAddressBuilder;
+pub use get_transfers::{GetTransfersBuilder, TransferQuery};
 pub use prepare_transfers::PrepareTransfersBuilder;
 pub use send_transfers::SendTransfersBuilder;
 pub use send_trytes::SendTrytesBuilder;
@@ -5,11 +5,13 @@ use iota_bundle_preview::{
     Address, Bundle, Hash, Index, Nonce, OutgoingBundleBuilder, Payload, Tag, Timestamp,
     TransactionBuilder, TransactionField, Value, PAYLOAD_TRIT_LEN,
 };
+use iota_constants::IOTA_SUPPLY;
 use iota_conversion::trytes_converter::to_trytes;
 use iota_crypto_preview::Kerl;
 use iota_signing_preview::{IotaSeed, WotsSecurityLevel};
 use iota_ternary_preview::{T1B1Buf, TritBuf, TryteBuf};
 
+use crate::error::Error;
 use crate::response::{Input, Transfer};
 use crate::Client;
 
@@ -26,7 +28,7 @@ pub struct PrepareTransfersBuilder<'a> {
 impl<'a> PrepareTransfersBuilder<'a> {
     pub(crate) fn new(seed: &'a IotaSeed<Kerl>) -> Self {
         Self {
-            seed: seed,
+            seed,
             transfers: Default::default(),
             security: 2,
             inputs: None,
@@ -59,9 +61,36 @@ impl<'a> PrepareTransfersBuilder<'a> {
         self
     }
 
+    /// Estimate how many transactions the bundle will have, e.g. to warn about the PoW time before sending.
+    /// Returns `None` for value transfers without custom inputs, since those are only searched on build.
+    pub fn transaction_count(&self) -> Option<usize> {
+        let total_output = self
+            .transfers
+            .iter()
+            .fold(0, |acc: u64, tx| acc.saturating_add(tx.value));
+        let inputs = match &self.inputs {
+            Some(i) => &i[..],
+            None if total_output == 0 => &[],
+            None => return None,
+        };
+        let total_input = inputs.iter().fold(0, |acc, tx| acc + tx.balance);
+
+        let outputs: usize = self.transfers.iter().map(Transfer::transaction_count).sum();
+        let signatures = inputs.len() * self.security as usize;
+        let remainder = if total_input > total_output { 1 } else { 0 };
+
+        Some(outputs + signatures + remainder)
+    }
+
     /// Send PrepareTransfers request
     pub async fn build(self) -> Result<Bundle> {
-        let total_output = self.transfers.iter().fold(0, |acc, tx| acc + tx.value);
+        let mut total_output: u64 = 0;
+        for transfer in &self.transfers {
+            total_output = total_output
+                .checked_add(transfer.value)
+                .filter(|total| *total <= IOTA_SUPPLY)
+                .ok_or(Error::InvalidTransferValue(transfer.value))?;
+        }
         let inputs = match self.inputs {
             Some(i) => i,
             None => {
@@ -77,7 +106,13 @@ impl<'a> PrepareTransfersBuilder<'a> {
         let total_input = inputs.iter().fold(0, |acc, tx| acc + tx.balance);
 
         let need_remainder = match total_input.cmp(&total_output) {
-            Ordering::Less => return Err(anyhow!("Inputs balance is insufficient.")),
+            Ordering::Less => {
+                return Err(Error::InsufficientBalance {
+                    needed: total_output,
+                    available: total_input,
+                }
+                .into())
+            }
             Ordering::Greater => true,
             Ordering::Equal => false,
         };
@@ -118,8 +153,8 @@ impl<'a> PrepareTransfersBuilder<'a> {
                             .with_bundle(Hash::zeros())
                             .with_trunk(Hash::zeros())
                             .with_branch(Hash::zeros())
-                            .with_attachment_lbts(Timestamp::from_inner_unchecked(std::u64::MIN))
-                            .with_attachment_ubts(Timestamp::from_inner_unchecked(std::u64::MAX))
+                            .with_attachment_lbts(Timestamp::from_inner_unchecked(u64::MIN))
+                            .with_attachment_ubts(Timestamp::from_inner_unchecked(u64::MAX))
                             .with_nonce(Nonce::zeros()),
                     );
                     value = 0;
@@ -140,8 +175,8 @@ impl<'a> PrepareTransfersBuilder<'a> {
                         .with_bundle(Hash::zeros())
                         .with_trunk(Hash::zeros())
                         .with_branch(Hash::zeros())
-                        .with_attachment_lbts(Timestamp::from_inner_unchecked(std::u64::MIN))
-                        .with_attachment_ubts(Timestamp::from_inner_unchecked(std::u64::MAX))
+                        .with_attachment_lbts(Timestamp::from_inner_unchecked(u64::MIN))
+                        .with_attachment_ubts(Timestamp::from_inner_unchecked(u64::MAX))
                         .with_nonce(Nonce::zeros()),
                 );
             }
@@ -163,8 +198,8 @@ impl<'a> PrepareTransfersBuilder<'a> {
                     .with_bundle(Hash::zeros())
                     .with_trunk(Hash::zeros())
                     .with_branch(Hash::zeros())
-                    .with_attachment_lbts(Timestamp::from_inner_unchecked(std::u64::MIN))
-                    .with_attachment_ubts(Timestamp::from_inner_unchecked(std::u64::MAX))
+                    .with_attachment_lbts(Timestamp::from_inner_unchecked(u64::MIN))
+                    .with_attachment_ubts(Timestamp::from_inner_unchecked(u64::MAX))
                     .with_nonce(Nonce::zeros()),
             );
 
@@ -185,8 +220,8 @@ impl<'a> PrepareTransfersBuilder<'a> {
                         .with_bundle(Hash::zeros())
                         .with_trunk(Hash::zeros())
                         .with_branch(Hash::zeros())
-                        .with_attachment_lbts(Timestamp::from_inner_unchecked(std::u64::MIN))
-                        .with_attachment_ubts(Timestamp::from_inner_unchecked(std::u64::MAX))
+                        .with_attachment_lbts(Timestamp::from_inner_unchecked(u64::MIN))
+                        .with_attachment_ubts(Timestamp::from_inner_unchecked(u64::MAX))
                         .with_nonce(Nonce::zeros()),
                 );
             }
@@ -196,10 +231,12 @@ impl<'a> PrepareTransfersBuilder<'a> {
         if need_remainder {
             let remainder = match self.remainder {
                 Some(r) => r,
+                // Addresses which were spent from are skipped, so the change never lands on a
+                // compromised address.
                 None => {
                     Client::get_new_address(self.seed)
                         .security(self.security)
-                        .index(inputs.last().unwrap().index + 1)
+                        .index(inputs.iter().map(|input| input.index).max().unwrap() + 1)
                         .generate()
                         .await?
                         .1
@@ -222,8 +259,8 @@ impl<'a> PrepareTransfersBuilder<'a> {
                     .with_bundle(Hash::zeros())
                     .with_trunk(Hash::zeros())
                     .with_branch(Hash::zeros())
-                    .with_attachment_lbts(Timestamp::from_inner_unchecked(std::u64::MIN))
-                    .with_attachment_ubts(Timestamp::from_inner_unchecked(std::u64::MAX))
+                    .with_attachment_lbts(Timestamp::from_inner_unchecked(u64::MIN))
+                    .with_attachment_ubts(Timestamp::from_inner_unchecked(u64::MAX))
                     .with_nonce(Nonce::zeros()),
             );
         }
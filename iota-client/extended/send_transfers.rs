@@ -1,8 +1,11 @@
 use anyhow::Result;
-use iota_bundle_preview::{Address, Hash, Transaction};
+use iota_bundle_preview::{Address, Hash, Transaction, TransactionField};
+use iota_conversion::Trinary;
 use iota_crypto_preview::Kerl;
 use iota_signing_preview::IotaSeed;
 
+use crate::client::Depth;
+use crate::error::Error;
 use crate::response::{Input, Transfer};
 use crate::Client;
 
@@ -14,22 +17,26 @@ pub struct SendTransfersBuilder<'a> {
     security: u8,
     inputs: Option<Vec<Input>>,
     remainder: Option<Address>,
-    depth: u8,
+    depth: Depth,
     min_weight_magnitude: u8,
     reference: Option<Hash>,
+    tip_retries: usize,
+    dust_threshold: Option<u64>,
 }
 
 impl<'a> SendTransfersBuilder<'a> {
     pub(crate) fn new(seed: &'a IotaSeed<Kerl>) -> Self {
         Self {
-            seed: seed,
+            seed,
             transfers: Default::default(),
             security: 2,
             inputs: None,
             remainder: None,
-            depth: 3,
+            depth: Depth::Auto,
             min_weight_magnitude: 14,
             reference: Default::default(),
+            tip_retries: 3,
+            dust_threshold: None,
         }
     }
 
@@ -58,9 +65,10 @@ impl<'a> SendTransfersBuilder<'a> {
         self
     }
 
-    /// The depth of the random walk for GTTA
-    pub fn depth(mut self, depth: u8) -> Self {
-        self.depth = depth;
+    /// The depth of the random walk for GTTA, a number of milestones or `Depth::Auto`. Default is
+    /// `Depth::Auto`.
+    pub fn depth(mut self, depth: impl Into<Depth>) -> Self {
+        self.depth = depth.into();
         self
     }
 
@@ -76,8 +84,68 @@ impl<'a> SendTransfersBuilder<'a> {
         self
     }
 
-    /// Send SendTransfers request
+    /// Set how many times tips are requested again if they are inconsistent. Default is 3.
+    pub fn tip_retries(mut self, tip_retries: usize) -> Self {
+        self.tip_retries = tip_retries;
+        self
+    }
+
+    /// Reject outputs of less than the given value to addresses which don't already hold at least
+    /// that much, like nodes with dust protection do. Default is no check.
+    pub fn dust_threshold(mut self, threshold: u64) -> Self {
+        self.dust_threshold = Some(threshold);
+        self
+    }
+
+    /// Send SendTransfers request. If custom inputs are given, their current balance is checked
+    /// first, so transfers they can't cover fail with [`Error::InsufficientBalance`] before any
+    /// signing or PoW. Likewise, outputs below the [`dust_threshold`] fail with [`Error::DustOutput`].
+    ///
+    /// [`Error::InsufficientBalance`]: ../error/enum.Error.html#variant.InsufficientBalance
+    /// [`dust_threshold`]: #method.dust_threshold
+    /// [`Error::DustOutput`]: ../error/enum.Error.html#variant.DustOutput
     pub async fn send(self) -> Result<Vec<Transaction>> {
+        let needed = self
+            .transfers
+            .iter()
+            .fold(0, |acc: u64, tx| acc.saturating_add(tx.value));
+        if let Some(inputs) = self.inputs.as_ref().filter(|_| needed > 0) {
+            let addresses: Vec<Address> = inputs.iter().map(|i| i.address.clone()).collect();
+            let available = Client::get_balances()
+                .addresses(&addresses)
+                .send()
+                .await?
+                .balances
+                .iter()
+                .fold(0, |acc: u64, balance| acc.saturating_add(*balance));
+            if available < needed {
+                return Err(Error::InsufficientBalance { needed, available }.into());
+            }
+        }
+
+        if let Some(threshold) = self.dust_threshold {
+            let dust: Vec<&Transfer> = self
+                .transfers
+                .iter()
+                .filter(|tx| tx.value > 0 && tx.value < threshold)
+                .collect();
+            let addresses: Vec<Address> = dust.iter().map(|tx| tx.address.clone()).collect();
+            let balances = Client::get_balances()
+                .addresses(&addresses)
+                .send()
+                .await?
+                .balances;
+            for (tx, balance) in dust.iter().zip(balances) {
+                if balance < threshold {
+                    return Err(Error::DustOutput {
+                        address: tx.address.to_inner().as_i8_slice().trytes().unwrap(),
+                        value: tx.value,
+                    }
+                    .into());
+                }
+            }
+        }
+
         let mut transfer = Client::prepare_transfers(self.seed)
             .transfers(self.transfers)
             .security(self.security);
@@ -90,17 +158,18 @@ impl<'a> SendTransfersBuilder<'a> {
             transfer = transfer.remainder(remainder);
         }
 
-        let mut trytes: Vec<Transaction> = transfer.build().await?.into_iter().map(|x| x).collect();
+        let mut trytes: Vec<Transaction> = transfer.build().await?.into_iter().collect();
         trytes.reverse();
         let mut send_trytes = Client::send_trytes()
             .trytes(trytes)
             .depth(self.depth)
-            .min_weight_magnitude(self.min_weight_magnitude);
+            .min_weight_magnitude(self.min_weight_magnitude)
+            .tip_retries(self.tip_retries);
 
         if let Some(reference) = self.reference {
             send_trytes = send_trytes.reference(reference);
         }
 
-        Ok(send_trytes.send().await?)
+        send_trytes.send().await
     }
 }
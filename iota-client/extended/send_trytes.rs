@@ -1,32 +1,56 @@
 use anyhow::Result;
-use iota_bundle_preview::{Hash, Transaction};
+use iota_bundle_preview::{Hash, Transaction, TransactionField};
+use iota_conversion::Trinary;
 use iota_crypto_preview::{Kerl, Sponge};
 use iota_ternary_preview::{T1B1Buf, TritBuf};
 
+use crate::client::Depth;
+use crate::error::Error;
 use crate::Client;
 
+/// Reservation of a bundle being sent, released when the send finishes or fails
+struct Reservation(Hash);
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        if let Ok(mut sent) = Client::get().sent_bundles.write() {
+            sent.release(&self.0);
+        }
+    }
+}
+
 /// Builder to construct sendTrytes API
 //#[derive(Debug)]
 pub struct SendTrytesBuilder {
     trytes: Vec<Transaction>,
-    depth: u8,
+    depth: Depth,
     min_weight_magnitude: u8,
     reference: Option<Hash>,
+    tip_retries: usize,
+    reattach: bool,
 }
 
 impl SendTrytesBuilder {
     pub(crate) fn new() -> Self {
         Self {
             trytes: Default::default(),
-            depth: Default::default(),
+            depth: Depth::Fixed(0),
             min_weight_magnitude: Default::default(),
             reference: Default::default(),
+            tip_retries: 3,
+            reattach: false,
         }
     }
 
-    /// The depth of the random walk for GTTA
-    pub fn depth(mut self, depth: u8) -> Self {
-        self.depth = depth;
+    // Sends the bundle even if it has been sent before
+    pub(crate) fn reattach(mut self) -> Self {
+        self.reattach = true;
+        self
+    }
+
+    /// The depth of the random walk for GTTA, a number of milestones or `Depth::Auto`
+    pub fn depth(mut self, depth: impl Into<Depth>) -> Self {
+        self.depth = depth.into();
         self
     }
 
@@ -48,13 +72,60 @@ impl SendTrytesBuilder {
         self
     }
 
+    /// Set how many times tips are requested again if they are inconsistent. Default is 3.
+    pub fn tip_retries(mut self, tip_retries: usize) -> Self {
+        self.tip_retries = tip_retries;
+        self
+    }
+
     /// Send SendTrytes request
     pub async fn send(self) -> Result<Vec<Transaction>> {
-        let mut gtta = Client::get_transactions_to_approve().depth(self.depth);
-        if let Some(hash) = self.reference {
-            gtta = gtta.reference(&hash);
-        }
-        let res = gtta.send().await?;
+        let bundle = self.trytes.first().map(|tx| *tx.bundle());
+        let _reservation = match (bundle, self.reattach) {
+            (Some(bundle), false) => {
+                let mut sent = Client::get()
+                    .sent_bundles
+                    .write()
+                    .map_err(|_| anyhow!("Sent bundles write poisened"))?;
+                if let Some(transactions) = sent.get(&bundle) {
+                    return Ok(transactions.clone());
+                }
+                // Storing and broadcasting it concurrently would send the bundle twice
+                if !sent.reserve(bundle) {
+                    let bundle = bundle.as_bytes().trytes().map_err(|e| anyhow!("{}", e))?;
+                    return Err(Error::BundleInFlight(bundle).into());
+                }
+                Some(Reservation(bundle))
+            }
+            _ => None,
+        };
+
+        // Value transfers need fresh tips, and so does every retry after inconsistent tips.
+        let has_value = self.trytes.iter().any(|tx| *tx.value().to_inner() != 0);
+        // Resolved once, so retries don't fetch the node info again
+        let depth = self.depth.resolve().await?;
+        let mut attempts = 0;
+        let res = loop {
+            let mut gtta = Client::get_transactions_to_approve()
+                .depth(depth)
+                .fresh(has_value || attempts > 0);
+            if let Some(hash) = self.reference {
+                gtta = gtta.reference(&hash);
+            }
+            let res = gtta.send().await?;
+            attempts += 1;
+
+            // Attaching to inconsistent tips would make the transactions inconsistent as well
+            if Client::check_consistency(&[res.trunk_transaction, res.branch_transaction])
+                .await?
+                .state
+            {
+                break res;
+            }
+            if attempts > self.tip_retries {
+                return Err(Error::CouldNotGetConsistentTips(attempts).into());
+            }
+        };
         let mut trunk = res.trunk_transaction.as_trits().to_owned();
         let mut trytes = Vec::new();
         for tx in self.trytes {
@@ -79,6 +150,13 @@ impl SendTrytesBuilder {
 
         Client::store_and_broadcast(&res).await?;
 
+        if let Some(bundle) = bundle {
+            Client::get()
+                .sent_bundles
+                .write()
+                .map_err(|_| anyhow!("Sent bundles write poisened"))?
+                .insert(bundle, res.clone());
+        }
         Ok(res)
     }
 }
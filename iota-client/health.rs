@@ -0,0 +1,69 @@
+//! Health tracking of the nodes in the node pool
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use std::time::Duration;
+
+/// When a failing node gets benched
+#[derive(Clone, Copy, Debug)]
+pub struct HealthPolicy {
+    /// Consecutive failures after which a node is benched. Default is 3.
+    pub max_failures: usize,
+    /// How long a benched node is only used if no other node is available. Default is 60 seconds.
+    pub cooldown: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: 3,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Health state of a node in the node pool
+#[derive(Clone, Debug)]
+pub struct NodeHealth {
+    /// URL of the node
+    pub url: Url,
+    /// Number of failed requests since the last successful one
+    pub consecutive_failures: usize,
+    /// Time of the last successful request
+    pub last_success: Option<DateTime<Utc>>,
+    /// Time until which the node is benched
+    pub benched_until: Option<DateTime<Utc>>,
+}
+
+impl NodeHealth {
+    pub(crate) fn new(url: Url) -> Self {
+        Self {
+            url,
+            consecutive_failures: 0,
+            last_success: None,
+            benched_until: None,
+        }
+    }
+
+    /// Whether the node is currently benched
+    pub fn is_benched(&self) -> bool {
+        match self.benched_until {
+            Some(until) => until > Utc::now(),
+            None => false,
+        }
+    }
+
+    pub(crate) fn record(&mut self, success: bool, policy: &HealthPolicy) {
+        if success {
+            self.consecutive_failures = 0;
+            self.last_success = Some(Utc::now());
+            self.benched_until = None;
+        } else {
+            self.consecutive_failures += 1;
+            if self.consecutive_failures >= policy.max_failures {
+                self.benched_until = chrono::Duration::from_std(policy.cooldown)
+                    .ok()
+                    .and_then(|cooldown| Utc::now().checked_add_signed(cooldown));
+            }
+        }
+    }
+}
@@ -18,10 +18,20 @@ extern crate serde_json;
 #[macro_use]
 pub mod client;
 pub mod core;
+pub mod error;
 pub mod extended;
+pub mod health;
 pub mod quorum;
 pub mod response;
 mod util;
 
-pub use client::Client;
+pub use client::{
+    Client, ConnectionPool, Depth, Timeouts, CONFIDENCE_SAMPLES, DEFAULT_API_VERSION,
+    DEFAULT_BROADCAST_CHUNK_SIZE, DEFAULT_DEPTH, DEFAULT_MAX_GET_TRYTES, DEFAULT_MAX_REQUESTS_LIST,
+    MAX_ADDRESSES_PER_REQUEST, MAX_AUTO_DEPTH, MAX_BUNDLE_LENGTH, MAX_CONCURRENT_BROADCASTS,
+    MAX_CONCURRENT_GET_TRYTES, MAX_SENT_BUNDLES,
+};
+pub use error::Error;
+pub use health::{HealthPolicy, NodeHealth};
 pub use response::*;
+pub use util::{add_checksum, transaction_hash, validate_transaction, value_sum};
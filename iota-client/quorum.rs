@@ -19,6 +19,9 @@ macro_rules! get_node_pool {
             .clone()
             .read()
             .map_err(|_| anyhow!("Node pool read poinsened"))?
+            .iter()
+            .cloned()
+            .collect::<Vec<_>>()
     };
 }
 
@@ -93,8 +96,8 @@ impl GetBalancesBuilder {
         }
 
         let mut result = HashMap::new();
-        for node in get_node_pool!().iter() {
-            let node = node.clone();
+        let pool = get_node_pool!();
+        for node in pool {
             let res: GetBalancesResponseBuilder = response!(client, body, node);
             let res = res.build().await?;
             let counters = result.entry(res).or_insert(0);
@@ -170,8 +173,8 @@ impl GetInclusionStatesBuilder {
         }
 
         let mut result = HashMap::new();
-        for node in get_node_pool!().iter() {
-            let node = node.clone();
+        let pool = get_node_pool!();
+        for node in pool {
             let res: GetInclusionStatesResponseBuilder = response!(client, body, node);
             let res = res.build().await?;
             let counters = result.entry(res).or_insert(0);
@@ -210,8 +213,8 @@ pub async fn get_latest_solid_subtangle_milestone() -> Result<Hash> {
     });
 
     let mut result = HashMap::new();
-    for node in get_node_pool!().iter() {
-        let node = node.clone();
+    let pool = get_node_pool!();
+    for node in pool {
         let hash: GetNodeInfoResponse = response!(client, body, node);
         let hash = Hash::from_inner_unchecked(
             // TODO missing impl error on Hash
@@ -249,8 +252,8 @@ pub async fn were_addresses_spent_from(
     });
 
     let mut result = HashMap::new();
-    for node in get_node_pool!().iter() {
-        let node = node.clone();
+    let pool = get_node_pool!();
+    for node in pool {
         let res: WereAddressesSpentFromResponseBuilder = response!(client, body, node);
         let res = res.build().await?;
         let counters = result.entry(res).or_insert(0);
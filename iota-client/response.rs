@@ -1,9 +1,13 @@
 //! Response types
 use anyhow::Result;
-use iota_bundle_preview::{Address, Hash, Tag, Transaction, TransactionField};
+use iota_bundle_preview::{Address, Hash, Tag, Transaction, TransactionField, PAYLOAD_TRIT_LEN};
+use iota_constants::IOTA_SUPPLY;
+use iota_conversion::{normalize_tag, trytes_converter::to_trytes};
 use iota_ternary_preview::TryteBuf;
 use serde::ser::{Serialize, SerializeSeq, SerializeStruct, Serializer};
 
+use crate::error::Error;
+
 // TODO: remove this struct once iota_bundle_preview::Transaction implements Serialize
 /// a Transaction wrapper that can be serialized
 #[derive(Serialize)]
@@ -47,6 +51,16 @@ impl From<&Transaction> for TransactionDef {
     }
 }
 
+/// Checks if the error message of a node means it doesn't know the command, e.g. IRI replies
+/// `Command [getInclusionStates] is unknown` for commands it dropped.
+fn is_unsupported_command(error: &str) -> bool {
+    let error = error.to_lowercase();
+    error.contains("command")
+        && (error.contains("unknown")
+            || error.contains("not supported")
+            || error.contains("unsupported"))
+}
+
 fn transaction_serializer<S>(x: &Vec<Transaction>, s: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -58,6 +72,17 @@ where
     seq.end()
 }
 
+fn optional_transaction_serializer<S>(x: &[Option<Transaction>], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut seq = s.serialize_seq(Some(x.len()))?;
+    for e in x {
+        seq.serialize_element(&e.as_ref().map(TransactionDef::from))?;
+    }
+    seq.end()
+}
+
 /// addNeighbors Response Type
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AddNeighborsResponse {
@@ -97,7 +122,7 @@ impl ConsistencyResponseBuilder {
         }
 
         Ok(ConsistencyResponse {
-            state: state,
+            state,
             info: self.info,
         })
     }
@@ -128,8 +153,7 @@ impl AttachToTangleResponseBuilder {
         } else if let Some(s) = self.trytes {
             s.iter().for_each(|x| {
                 trytes.push(
-                    Transaction::from_trits(TryteBuf::try_from_str(&x).unwrap().as_trits())
-                        .unwrap(),
+                    Transaction::from_trits(TryteBuf::try_from_str(x).unwrap().as_trits()).unwrap(),
                 )
             });
         }
@@ -154,6 +178,19 @@ impl ErrorResponseBuilder {
 
         Ok(())
     }
+
+    // Whether the node refused transactions because it already has them
+    pub(crate) fn is_duplicate(&self) -> bool {
+        self.exception
+            .iter()
+            .chain(self.error.iter())
+            .map(|message| message.to_lowercase())
+            .any(|message| {
+                message.contains("duplicate")
+                    || message.contains("already known")
+                    || message.contains("already exists")
+            })
+    }
 }
 
 /// findTransactions Response Type
@@ -189,7 +226,7 @@ pub(crate) struct FindTransactionsResponseBuilder {
 
 impl FindTransactionsResponseBuilder {
     pub(crate) async fn build(self) -> Result<FindTransactionsResponse> {
-        let mut hashes: Vec<Hash> = Vec::new();
+        let hashes: Vec<Hash>;
         if let Some(exception) = self.exception {
             return Err(anyhow!("{}", exception));
         } else if let Some(error) = self.error {
@@ -199,10 +236,14 @@ impl FindTransactionsResponseBuilder {
                 .iter()
                 .map(|s| {
                     Hash::from_inner_unchecked(
-                        TryteBuf::try_from_str(&s).unwrap().as_trits().encode(),
+                        TryteBuf::try_from_str(s).unwrap().as_trits().encode(),
                     )
                 })
                 .collect::<Vec<Hash>>();
+        } else {
+            // Only an explicit empty list means nothing was found, callers rely on it to tell
+            // unused addresses apart.
+            return Err(anyhow!("Node response is missing the hashes field"));
         }
 
         Ok(FindTransactionsResponse { hashes })
@@ -276,7 +317,7 @@ impl GetBalancesResponseBuilder {
                 .iter()
                 .map(|s| {
                     Hash::from_inner_unchecked(
-                        TryteBuf::try_from_str(&s).unwrap().as_trits().encode(),
+                        TryteBuf::try_from_str(s).unwrap().as_trits().encode(),
                     )
                 })
                 .collect::<Vec<Hash>>();
@@ -307,6 +348,9 @@ impl GetInclusionStatesResponseBuilder {
         if let Some(exception) = self.exception {
             return Err(anyhow!("{}", exception));
         } else if let Some(error) = self.error {
+            if is_unsupported_command(&error) {
+                return Err(Error::UnsupportedCommand("getInclusionStates".to_string()).into());
+            }
             return Err(anyhow!("{}", error));
         } else if let Some(s) = self.states {
             states = s;
@@ -367,6 +411,8 @@ pub struct GetNodeAPIConfigurationResponse {
     /// Milestone start index on IRI node
     #[serde(rename = "milestoneStartIndex")]
     pub milestone_start_index: i64,
+    /// Minimum weight magnitude of the network, if the node advertises it
+    pub mwm: Option<usize>,
 }
 
 /// getNodeInfo Response Type
@@ -420,6 +466,49 @@ pub struct GetNodeInfoResponse {
     /// Transactions to request on IRI node
     #[serde(rename = "transactionsToRequest")]
     pub transactions_to_request: u32,
+    /// Optional features the node has enabled, e.g. `RemotePOW`
+    pub features: Option<Vec<String>>,
+}
+
+impl GetNodeInfoResponse {
+    /// Latest milestone of the node
+    pub fn latest_milestone(&self) -> Result<Milestone> {
+        Milestone::new(&self.latest_milestone, self.latest_milestone_index)
+    }
+
+    /// Latest solid subtangle milestone of the node
+    pub fn latest_solid_milestone(&self) -> Result<Milestone> {
+        Milestone::new(
+            &self.latest_solid_subtangle_milestone,
+            self.latest_solid_subtangle_milestone_index,
+        )
+    }
+}
+
+/// Milestone reported by a node
+#[derive(Clone, Debug)]
+pub struct Milestone {
+    /// Milestone transaction hash
+    pub hash: Hash,
+    /// Milestone index
+    pub index: u32,
+}
+
+impl Milestone {
+    fn new(hash: &str, index: u32) -> Result<Self> {
+        if hash.len() != 81 {
+            return Err(anyhow!("Invalid milestone hash: {}", hash));
+        }
+        let hash = TryteBuf::try_from_str(hash)
+            .map_err(|_| anyhow!("Invalid milestone hash: {}", hash))?
+            .as_trits()
+            .encode();
+
+        Ok(Self {
+            hash: Hash::from_inner_unchecked(hash),
+            index,
+        })
+    }
 }
 
 /// getTips Response Type
@@ -529,9 +618,10 @@ pub struct NeighborResponse {
 /// getTrytes Response Type
 #[derive(Serialize)]
 pub struct GetTrytesResponse {
-    /// Vector of transaction trytes for the given transaction hashes (in the same order as the parameters)
-    #[serde(serialize_with = "transaction_serializer")]
-    pub trytes: Vec<Transaction>,
+    /// Vector of transactions for the given transaction hashes (in the same order as the parameters).
+    /// Transactions unknown to the node are `None`.
+    #[serde(serialize_with = "optional_transaction_serializer")]
+    pub trytes: Vec<Option<Transaction>>,
 }
 
 #[derive(Clone, Deserialize, Debug)]
@@ -550,10 +640,15 @@ impl GetTrytesResponseBuilder {
             return Err(anyhow!("{}", error));
         } else if let Some(s) = self.trytes {
             s.iter().for_each(|x| {
-                trytes.push(
-                    Transaction::from_trits(TryteBuf::try_from_str(&x).unwrap().as_trits())
-                        .unwrap(),
-                )
+                // Nodes return all 9s for transactions they don't know
+                if x.chars().all(|c| c == '9') {
+                    trytes.push(None);
+                } else {
+                    trytes.push(Some(
+                        Transaction::from_trits(TryteBuf::try_from_str(x).unwrap().as_trits())
+                            .unwrap(),
+                    ));
+                }
             });
         }
 
@@ -599,6 +694,17 @@ impl WereAddressesSpentFromResponseBuilder {
     }
 }
 
+/// Balance and spent state of an address
+#[derive(Clone, Debug, Serialize)]
+pub struct AddressState {
+    /// The address
+    pub address: Address,
+    /// Balance of the address
+    pub balance: u64,
+    /// Whether the address was ever spent from
+    pub spent: bool,
+}
+
 #[derive(Clone, Debug, Serialize)]
 /// Address can be used as input to spend balance
 pub struct Input {
@@ -607,6 +713,17 @@ pub struct Input {
     pub(crate) index: u64,
 }
 
+impl Input {
+    /// Create an input from an address of the seed, its balance and its key index
+    pub fn new(address: Address, balance: u64, index: u64) -> Self {
+        Self {
+            address,
+            balance,
+            index,
+        }
+    }
+}
+
 /// A transfer could be an input or an output for building a bundle.
 /// input/output transfer depends on value, a negative value for an output transfer, a positive value for an input transfer
 #[derive(Clone, Debug)]
@@ -620,3 +737,98 @@ pub struct Transfer {
     /// Optional message
     pub tag: Option<Tag>,
 }
+
+impl Transfer {
+    /// Builder to construct a transfer from trytes, validating them on build
+    pub fn builder() -> TransferBuilder {
+        TransferBuilder::default()
+    }
+
+    /// Number of transactions the transfer takes in a bundle. Each message character is encoded as
+    /// two trytes, and every started payload fragment needs its own transaction.
+    pub fn transaction_count(&self) -> usize {
+        match &self.message {
+            Some(message) => (message.len() * 6).div_ceil(PAYLOAD_TRIT_LEN),
+            None => 1,
+        }
+    }
+}
+
+/// Builder to construct a Transfer
+#[derive(Clone, Debug, Default)]
+pub struct TransferBuilder {
+    address: Option<String>,
+    value: u64,
+    message: Option<String>,
+    tag: Option<String>,
+}
+
+impl TransferBuilder {
+    /// Set the address to transfer to, as 81 trytes (do not include the checksum)
+    pub fn address(mut self, address: &str) -> Self {
+        self.address = Some(address.to_string());
+        self
+    }
+
+    /// Set the value to transfer, default is 0
+    pub fn value(mut self, value: u64) -> Self {
+        self.value = value;
+        self
+    }
+
+    /// Set the message, which has to be ascii. Messages longer than a payload fragment are split
+    /// into several transactions, see [`Transfer::transaction_count`].
+    ///
+    /// [`Transfer::transaction_count`]: struct.Transfer.html#method.transaction_count
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    /// Set the tag, up to 27 trytes. It is uppercased and padded with `9`.
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
+    /// Validate the fields and build the transfer
+    pub fn build(self) -> Result<Transfer> {
+        let address = self
+            .address
+            .ok_or_else(|| anyhow!("Transfer address is missing"))?;
+        if address.len() != iota_constants::ADDRESS_LENGTH_WITHOUT_CHECKSUM {
+            return Err(anyhow!("Invalid address length: {}", address.len()));
+        }
+        let address = Address::from_inner_unchecked(
+            TryteBuf::try_from_str(&address)
+                .map_err(|_| anyhow!("Invalid address trytes: {}", address))?
+                .as_trits()
+                .encode(),
+        );
+
+        if self.value > IOTA_SUPPLY {
+            return Err(Error::InvalidTransferValue(self.value).into());
+        }
+
+        if let Some(message) = &self.message {
+            to_trytes(message).map_err(|e| anyhow!("Invalid message: {}", e))?;
+        }
+
+        let tag = match self.tag {
+            Some(tag) => {
+                let tag = normalize_tag(&tag).map_err(|e| anyhow!("{}", e))?;
+                Some(Tag::from_inner_unchecked(
+                    TryteBuf::try_from_str(&tag).unwrap().as_trits().encode(),
+                ))
+            }
+            None => None,
+        };
+
+        Ok(Transfer {
+            address,
+            value: self.value,
+            message: self.message,
+            tag,
+        })
+    }
+}
@@ -56,7 +56,7 @@ async fn test_broadcast_bundle() {
 #[smol_potat::test]
 async fn test_broadcast_transactions() {
     client_init();
-    let _ = Client::broadcast_transactions(&[tx()]).await.unwrap();
+    Client::broadcast_transactions(&[tx()]).await.unwrap();
 }
 
 #[smol_potat::test]
@@ -333,7 +333,7 @@ async fn test_is_address_used() {
     .await
     .unwrap();
 
-    assert_eq!(res, false);
+    assert!(!res);
 }
 
 #[smol_potat::test]
@@ -488,7 +488,7 @@ async fn test_were_addresses_spent_from() {
     .await
     .unwrap();
 
-    assert_eq!(res.states[0], false);
+    assert!(!res.states[0]);
 }
 
 fn tx() -> Transaction {
@@ -19,9 +19,402 @@ pub const TEST_BUNDLE_TX_1: &str =
 pub const TEST_MILESTONE_0: &str =
     "FBOCIRYP9IVIUER9URIZVPOMYZJSOJJHVTYLYTKLOPNCRJECEVELQSBHY9ESZLJTBHUNSQHNKWLUVP999";
 pub const TEST_TAG_0: &str = "CCLIENT99999999999999999999";
+pub const TEST_TX_HASH: &str =
+    "GNSMASOK9OLEQJTFVPNCQDHXHOOJJB9OLEFZM9GDWMNHKYFELSOZDRCXROZGSUPS9OUXNABVWZACA9999";
 pub const TEST_TX_TRYTES: &str = "BCDDPCADADXCBDVCEAKDXCHDWCEARBYBACXBOBCCEAHDXCDDGDTC9DTCRCHDJ9MBCDIDBDHDDBEAUAVAVAUAABUAJ9CCXCADTCGDHDPCADDDDBEAWAUAWAUARAUAXARAUAWACCUAABDBVAZADBXAABPAUAVADBUAUAJ9VCCCCCKBEAHDCDCDZCEAVAZASAYAUAXAGDEAMASCTCDDHDWCGBXANA9999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999999HORNET99SPAMMER99OLIVER99VPS9XL99999999999999999999999999999999999999999999999999999999999999999999999999999HORNET99INTEGRATED999999OE9VRJXAIIPF999999999999999999HOOEWOKSZZCBVXZERQFIL9DMBYVFYELVHWLJOMRJKLXYXZRBESBDDMYQUT9HSROAKUETMD9WZCBDDCUJXOKVJQXIRRORVXPMWA9SXSNSILRZBFAJWQPKXTZKPMAJFZORVH9FDYEXABQQOORJXLMUBWLBXKZSY99999OKVJQXIRRORVXPMWA9SXSNSILRZBFAJWQPKXTZKPMAJFZORVH9FDYEXABQQOORJXLMUBWLBXKZSY99999HORNET99INTEGRATED999999OE9VRJXAIIPF999999999K99999999AXD9999999LIK99999999999999";
 
 pub fn client_init() {
     //iota_client::Client::add_node("https://nodes.iota.cafe").unwrap();
     iota_client::Client::add_node("https://nodes.comnet.thetangle.org").unwrap();
 }
+
+use iota_bundle_preview::{
+    Address, Hash, Index, Nonce, Payload, Tag, Timestamp, Transaction, TransactionBuilder,
+    TransactionField, Value as TxValue,
+};
+use iota_client::transaction_hash;
+use iota_conversion::Trinary;
+use iota_crypto_preview::Kerl;
+use iota_signing_preview::{
+    IotaSeed, PrivateKey, PrivateKeyGenerator, PublicKey, Seed, WotsPrivateKeyGeneratorBuilder,
+    WotsSecurityLevel,
+};
+use iota_ternary_preview::{T1B1Buf, TritBuf, TryteBuf};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+static CLIENT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Exclusive access to the node pool of the global client, so mock tests in a binary don't see
+/// each other's nodes.
+pub struct ClientLock(MutexGuard<'static, ()>);
+
+pub fn lock_client() -> ClientLock {
+    ClientLock(
+        CLIENT_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    )
+}
+
+type Handler = dyn Fn(&Value) -> (u16, String) + Send + Sync;
+type Headers = Vec<(String, String)>;
+
+/// A local HTTP server answering API calls with the given handler. It is added to the node pool
+/// on start and removed again on drop. Starting it also forgets the spent addresses of earlier tests.
+pub struct MockNode {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Value>>>,
+    headers: Arc<Mutex<Vec<Headers>>>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl MockNode {
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&Value) -> (u16, String) + Send + Sync + 'static,
+    {
+        Self::start_with(handler, false)
+    }
+
+    /// Like `start`, but keeps connections open for further requests instead of closing them
+    /// after the response.
+    pub fn start_keep_alive<F>(handler: F) -> Self
+    where
+        F: Fn(&Value) -> (u16, String) + Send + Sync + 'static,
+    {
+        Self::start_with(handler, true)
+    }
+
+    fn start_with<F>(handler: F, keep_alive: bool) -> Self
+    where
+        F: Fn(&Value) -> (u16, String) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let headers = Arc::new(Mutex::new(Vec::new()));
+        let connections = Arc::new(AtomicUsize::new(0));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let recorded = requests.clone();
+        let recorded_headers = headers.clone();
+        let accepted = connections.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => break,
+                };
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let handler = handler.clone();
+                let recorded = recorded.clone();
+                let recorded_headers = recorded_headers.clone();
+                thread::spawn(move || {
+                    serve(stream, &*handler, &recorded, &recorded_headers, keep_alive)
+                });
+            }
+        });
+
+        iota_client::Client::clear_spent_addresses();
+        iota_client::Client::clear_sent_bundles();
+        iota_client::Client::set_max_sent_bundles(iota_client::MAX_SENT_BUNDLES);
+        iota_client::Client::add_node(&url).unwrap();
+        Self {
+            url,
+            requests,
+            headers,
+            connections,
+        }
+    }
+
+    /// Bodies of all requests received so far
+    pub fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Headers of all requests received so far, with lowercase names
+    pub fn headers(&self) -> Vec<Headers> {
+        self.headers.lock().unwrap().clone()
+    }
+
+    /// Number of connections accepted so far
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    /// Commands of all requests received so far
+    pub fn commands(&self) -> Vec<String> {
+        self.requests()
+            .iter()
+            .map(|body| body["command"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        let _ = iota_client::Client::remove_node(&self.url);
+    }
+}
+
+fn serve(
+    stream: TcpStream,
+    handler: &Handler,
+    recorded: &Mutex<Vec<Value>>,
+    recorded_headers: &Mutex<Vec<Headers>>,
+    keep_alive: bool,
+) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    while serve_request(
+        &mut reader,
+        &mut stream,
+        handler,
+        recorded,
+        recorded_headers,
+        keep_alive,
+    ) && keep_alive
+    {}
+}
+
+/// Answers a single request, returns whether one was read
+fn serve_request(
+    reader: &mut BufReader<TcpStream>,
+    stream: &mut TcpStream,
+    handler: &Handler,
+    recorded: &Mutex<Vec<Value>>,
+    recorded_headers: &Mutex<Vec<Headers>>,
+    keep_alive: bool,
+) -> bool {
+    let mut length = 0;
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return false;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+            headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+    let mut body = vec![0; length];
+    if reader.read_exact(&mut body).is_err() {
+        return false;
+    }
+
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let (status, response) = handler(&body);
+    recorded.lock().unwrap().push(body);
+    recorded_headers.lock().unwrap().push(headers);
+
+    write!(
+        stream,
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: {}\r\n\r\n{}",
+        status,
+        response.len(),
+        if keep_alive { "keep-alive" } else { "close" },
+        response
+    )
+    .is_ok()
+}
+
+/// A getNodeInfo response of a synced node with the given latest solid subtangle milestone
+pub fn node_info(milestone: &str) -> String {
+    serde_json::json!({
+        "appName": "IRI",
+        "appVersion": "1.8.6",
+        "latestMilestone": milestone,
+        "latestMilestoneIndex": STARTING_MILESTONE_INDEX + 1,
+        "latestSolidSubtangleMilestone": milestone,
+        "latestSolidSubtangleMilestoneIndex": STARTING_MILESTONE_INDEX,
+        "milestoneStartIndex": 0,
+        "neighbors": 0,
+        "time": OLDER_TIMESTAMP * 1000,
+        "tips": 0,
+        "transactionsToRequest": 0,
+    })
+    .to_string()
+}
+
+pub fn hash(trytes: &str) -> Hash {
+    Hash::from_inner_unchecked(TryteBuf::try_from_str(trytes).unwrap().as_trits().encode())
+}
+
+pub fn address(trytes: &str) -> Address {
+    Address::from_inner_unchecked(TryteBuf::try_from_str(trytes).unwrap().as_trits().encode())
+}
+
+pub fn seed() -> IotaSeed<Kerl> {
+    IotaSeed::<Kerl>::from_buf(
+        TryteBuf::try_from_str(TEST_BUNDLE_TX_0)
+            .unwrap()
+            .as_trits()
+            .encode::<T1B1Buf>(),
+    )
+    .unwrap()
+}
+
+/// The address of the test seed at the given index
+pub fn seed_address(index: u64, security: WotsSecurityLevel) -> Address {
+    Address::from_inner_unchecked(
+        WotsPrivateKeyGeneratorBuilder::<Kerl>::default()
+            .security_level(security)
+            .build()
+            .unwrap()
+            .generate(&seed(), index)
+            .unwrap()
+            .generate_public_key()
+            .unwrap()
+            .trits()
+            .to_owned(),
+    )
+}
+
+/// A zero value transaction with the given indexes and trunk, with its hash and trytes
+pub fn transaction(index: usize, last_index: usize, trunk: Hash) -> (Hash, String) {
+    bundle_transaction(Address::zeros(), Hash::zeros(), index, last_index, trunk, 0)
+}
+
+/// A zero value transaction of the given bundle on the given address, with its hash and trytes
+pub fn bundle_transaction(
+    address: Address,
+    bundle: Hash,
+    index: usize,
+    last_index: usize,
+    trunk: Hash,
+    attachment_ts: u64,
+) -> (Hash, String) {
+    hashed(transaction_builder(
+        address,
+        bundle,
+        index,
+        last_index,
+        trunk,
+        attachment_ts,
+    ))
+}
+
+/// A transaction with the given indexes, trunk and value, with its hash and trytes
+pub fn value_transaction(
+    index: usize,
+    last_index: usize,
+    trunk: Hash,
+    value: i64,
+) -> (Hash, String) {
+    hashed(
+        transaction_builder(Address::zeros(), Hash::zeros(), index, last_index, trunk, 0)
+            .with_value(TxValue::from_inner_unchecked(value)),
+    )
+}
+
+/// A builder of a zero value transaction with the given fields
+pub fn transaction_builder(
+    address: Address,
+    bundle: Hash,
+    index: usize,
+    last_index: usize,
+    trunk: Hash,
+    attachment_ts: u64,
+) -> TransactionBuilder {
+    TransactionBuilder::new()
+        .with_payload(Payload::zeros())
+        .with_address(address)
+        .with_value(TxValue::from_inner_unchecked(0))
+        .with_obsolete_tag(Tag::zeros())
+        .with_timestamp(Timestamp::from_inner_unchecked(OLDER_TIMESTAMP))
+        .with_index(Index::from_inner_unchecked(index))
+        .with_last_index(Index::from_inner_unchecked(last_index))
+        .with_tag(Tag::zeros())
+        .with_attachment_ts(Timestamp::from_inner_unchecked(attachment_ts))
+        .with_bundle(bundle)
+        .with_trunk(trunk)
+        .with_branch(Hash::zeros())
+        .with_attachment_lbts(Timestamp::from_inner_unchecked(0))
+        .with_attachment_ubts(Timestamp::from_inner_unchecked(0))
+        .with_nonce(Nonce::zeros())
+}
+
+fn hashed(builder: TransactionBuilder) -> (Hash, String) {
+    let tx = builder.build().unwrap();
+    (transaction_hash(&tx), transaction_trytes(&tx))
+}
+
+pub fn transaction_trytes(tx: &Transaction) -> String {
+    let mut trits = TritBuf::<T1B1Buf>::zeros(8019);
+    tx.into_trits_allocated(&mut trits);
+    trits.as_i8_slice().trytes().unwrap()
+}
+
+/// Serves getTrytes, findTransactions by addresses or bundles, and wereAddressesSpentFrom
+/// (never spent) from the given transactions, keyed by their hashes
+pub fn tangle(transactions: Vec<(Hash, String)>) -> impl Fn(&Value) -> (u16, String) {
+    let transactions: HashMap<String, (String, Transaction)> = transactions
+        .into_iter()
+        .map(|(hash, trytes)| {
+            let tx = Transaction::from_trits(
+                TryteBuf::try_from_str(&trytes)
+                    .unwrap()
+                    .as_trits()
+                    .encode::<T1B1Buf>()
+                    .as_slice(),
+            )
+            .unwrap();
+            (hash.as_bytes().trytes().unwrap(), (trytes, tx))
+        })
+        .collect();
+    move |body| {
+        let strings = |field: &str| -> Vec<String> {
+            body[field]
+                .as_array()
+                .map(|values| {
+                    values
+                        .iter()
+                        .map(|value| value.as_str().unwrap().to_string())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let response = match body["command"].as_str().unwrap() {
+            "findTransactions" => {
+                let addresses = strings("addresses");
+                let bundles = strings("bundles");
+                let hashes: Vec<&String> = transactions
+                    .iter()
+                    .filter(|(_, (_, tx))| {
+                        addresses.contains(&tx.address().to_inner().as_i8_slice().trytes().unwrap())
+                            || bundles.contains(&tx.bundle().as_bytes().trytes().unwrap())
+                    })
+                    .map(|(hash, _)| hash)
+                    .collect();
+                serde_json::json!({ "hashes": hashes })
+            }
+            "wereAddressesSpentFrom" => {
+                serde_json::json!({ "states": vec![false; strings("addresses").len()] })
+            }
+            _ => {
+                let trytes: Vec<String> = strings("hashes")
+                    .iter()
+                    .map(|hash| match transactions.get(hash) {
+                        Some((trytes, _)) => trytes.clone(),
+                        None => "9".repeat(2673),
+                    })
+                    .collect();
+                serde_json::json!({ "trytes": trytes })
+            }
+        };
+        (200, response.to_string())
+    }
+}
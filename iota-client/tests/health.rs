@@ -0,0 +1,46 @@
+//! Node health tests against local mock nodes. They change the global client settings, so they
+//! live in their own binary.
+mod common;
+use crate::common::*;
+use iota_client::{Client, HealthPolicy};
+use std::thread;
+use std::time::Duration;
+
+#[smol_potat::test]
+async fn test_failing_node_is_benched() {
+    let _lock = lock_client();
+    Client::set_health_policy(HealthPolicy {
+        max_failures: 2,
+        cooldown: Duration::from_millis(500),
+    });
+
+    let failing = MockNode::start(|_| (500, "{}".to_string()));
+    for _ in 0..2 {
+        assert!(Client::get_node_info().await.is_err());
+    }
+    assert_eq!(failing.requests().len(), 2);
+
+    let healthy = MockNode::start(|_| (200, node_info(TEST_MILESTONE_0)));
+    for _ in 0..3 {
+        Client::get_node_info().await.unwrap();
+    }
+    assert_eq!(failing.requests().len(), 2);
+    assert_eq!(healthy.requests().len(), 3);
+
+    let health = Client::node_health().unwrap();
+    let failing_health = health
+        .iter()
+        .find(|h| h.url.as_str().trim_end_matches('/') == failing.url)
+        .unwrap();
+    assert_eq!(failing_health.consecutive_failures, 2);
+    assert!(failing_health.is_benched());
+
+    thread::sleep(Duration::from_millis(600));
+    let health = Client::node_health().unwrap();
+    assert!(health.iter().all(|h| !h.is_benched()));
+
+    drop(healthy);
+    assert!(Client::get_node_info().await.is_err());
+    assert_eq!(failing.requests().len(), 3);
+    Client::set_health_policy(HealthPolicy::default());
+}
@@ -0,0 +1,1709 @@
+//! Tests against a local mock node, they don't need network access.
+mod common;
+use crate::common::*;
+use futures::TryStreamExt;
+use iota_bundle_preview::{Address, Hash, Transaction, TransactionField};
+use iota_client::extended::TransferQuery;
+use iota_client::{
+    add_checksum, transaction_hash, Client, ConnectionPool, Depth, Error, Input, Timeouts,
+    Transfer, CONFIDENCE_SAMPLES, DEFAULT_API_VERSION, DEFAULT_BROADCAST_CHUNK_SIZE, DEFAULT_DEPTH,
+    MAX_AUTO_DEPTH, MAX_BUNDLE_LENGTH,
+};
+use iota_conversion::{indices_to_trytes, trytes_to_indices, Trinary};
+use iota_signing_preview::WotsSecurityLevel;
+use iota_ternary_preview::TryteBuf;
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[smol_potat::test]
+async fn test_get_latest_inclusion_unsupported_command() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str() {
+        Some("getNodeInfo") => (200, node_info(TEST_MILESTONE_0)),
+        Some(command) => (
+            400,
+            json!({ "error": format!("Command [{}] is unknown", command) }).to_string(),
+        ),
+        None => (400, json!({ "error": "Invalid request" }).to_string()),
+    });
+
+    let err = Client::get_latest_inclusion(&[hash(TEST_BUNDLE_TX_0)])
+        .await
+        .unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::UnsupportedCommand(command)) => assert_eq!(command, "getInclusionStates"),
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(node.commands(), vec!["getNodeInfo", "getInclusionStates"]);
+}
+
+#[smol_potat::test]
+async fn test_get_trytes_unknown_transaction() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|_| {
+        (
+            200,
+            json!({ "trytes": [TEST_TX_TRYTES, "9".repeat(2673)] }).to_string(),
+        )
+    });
+
+    let res = Client::get_trytes(&[hash(TEST_BUNDLE_TX_0), hash(TEST_BUNDLE_TX_1)])
+        .await
+        .unwrap();
+    assert_eq!(res.trytes.len(), 2);
+    assert!(res.trytes[0].is_some());
+    assert!(res.trytes[1].is_none());
+}
+
+#[smol_potat::test]
+async fn test_timeouts_per_command() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|body| {
+        thread::sleep(Duration::from_millis(500));
+        match body["command"].as_str() {
+            Some("attachToTangle") => (200, json!({ "trytes": [TEST_TX_TRYTES] }).to_string()),
+            _ => (200, node_info(TEST_MILESTONE_0)),
+        }
+    });
+    Client::set_timeouts(Timeouts {
+        default: Duration::from_millis(100),
+        attach: Duration::from_secs(5),
+    });
+
+    let node_info = Client::get_node_info().await;
+    let attached = Client::attach_to_tangle()
+        .trunk_transaction(&hash(TEST_TRUNK_HASH))
+        .branch_transaction(&hash(TEST_BRANCH_HASH))
+        .send()
+        .await;
+    Client::set_timeouts(Timeouts::default());
+
+    let err = node_info.unwrap_err();
+    assert!(err.downcast_ref::<reqwest::Error>().unwrap().is_timeout());
+    assert_eq!(attached.unwrap().trytes.len(), 1);
+}
+
+#[smol_potat::test]
+async fn test_api_version_header() {
+    let _lock = lock_client();
+    let node = MockNode::start(|_| (200, node_info(TEST_MILESTONE_0)));
+
+    Client::get_node_info().await.unwrap();
+    Client::set_api_version("2");
+    Client::get_node_info().await.unwrap();
+    Client::set_api_version(DEFAULT_API_VERSION);
+
+    let versions: Vec<String> = node
+        .headers()
+        .into_iter()
+        .map(|headers| {
+            headers
+                .into_iter()
+                .find(|(name, _)| name == "x-iota-api-version")
+                .map(|(_, value)| value)
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(versions, vec!["1", "2"]);
+}
+
+#[smol_potat::test]
+async fn test_connections_are_reused() {
+    let _lock = lock_client();
+    let node = MockNode::start_keep_alive(|_| (200, node_info(TEST_MILESTONE_0)));
+
+    for _ in 0..3 {
+        Client::get_node_info().await.unwrap();
+    }
+    assert_eq!(node.connections(), 1);
+
+    Client::set_connection_pool(ConnectionPool {
+        max_idle_per_host: 0,
+        ..ConnectionPool::default()
+    });
+    for _ in 0..3 {
+        Client::get_node_info().await.unwrap();
+    }
+    Client::set_connection_pool(ConnectionPool::default());
+    assert_eq!(node.connections(), 4);
+}
+
+#[smol_potat::test]
+async fn test_api_version_rejected() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|_| (400, json!({ "error": "Invalid API Version" }).to_string()));
+
+    let err = Client::get_node_info().await.unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::MissingApiVersion(version)) => assert_eq!(version, DEFAULT_API_VERSION),
+        _ => panic!("unexpected error: {}", err),
+    }
+}
+
+#[smol_potat::test]
+async fn test_unparsable_response() {
+    let _lock = lock_client();
+    let page = format!(
+        "<html><body><h1>502 Bad Gateway</h1>{}</body></html>",
+        "x".repeat(500)
+    );
+    let response = page.clone();
+    let _node = MockNode::start(move |_| (502, response.clone()));
+
+    let err = Client::get_node_info().await.unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::Deserialization { command, snippet }) => {
+            assert_eq!(command, "getNodeInfo");
+            assert!(snippet.starts_with("<html><body><h1>502 Bad Gateway</h1>"));
+            assert!(snippet.len() < page.len());
+        }
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert!(err.to_string().contains("getNodeInfo"));
+}
+
+#[smol_potat::test]
+async fn test_attach_to_tangle_without_remote_pow() {
+    let _lock = lock_client();
+    let node = MockNode::start(|_| {
+        let mut info: serde_json::Value =
+            serde_json::from_str(&node_info(TEST_MILESTONE_0)).unwrap();
+        info["features"] = json!(["WereAddressesSpentFrom"]);
+        (200, info.to_string())
+    });
+
+    let err = Client::attach_to_tangle()
+        .trunk_transaction(&hash(TEST_TRUNK_HASH))
+        .branch_transaction(&hash(TEST_BRANCH_HASH))
+        .send()
+        .await
+        .err()
+        .unwrap();
+    match err.downcast_ref::<Error>() {
+        Some(Error::UnsupportedCommand(command)) => assert_eq!(command, "attachToTangle"),
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(node.commands(), vec!["getNodeInfo"]);
+}
+
+#[smol_potat::test]
+async fn test_traverse_bundle_checks_hashes() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|_| (200, json!({ "trytes": [TEST_TX_TRYTES] }).to_string()));
+
+    let bundle = Client::traverse_bundle(&hash(TEST_TX_HASH)).await.unwrap();
+    assert_eq!(bundle.len(), 1);
+    assert!(Client::traverse_bundle(&hash(TEST_BUNDLE_TX_0))
+        .await
+        .is_err());
+}
+
+#[smol_potat::test]
+async fn test_get_latest_milestones() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|_| (200, node_info(TEST_MILESTONE_0)));
+
+    let latest = Client::get_latest_milestone().await.unwrap();
+    let solid = Client::get_latest_solid_milestone().await.unwrap();
+    assert_eq!(latest.index, STARTING_MILESTONE_INDEX + 1);
+    assert_eq!(solid.index, STARTING_MILESTONE_INDEX);
+    assert_eq!(solid.hash, hash(TEST_MILESTONE_0));
+}
+
+#[smol_potat::test]
+async fn test_traverse_bundle_self_referencing_trunk() {
+    let _lock = lock_client();
+    let trunk = hash(TEST_TX_HASH);
+    let (_, trytes) = transaction(0, 1, trunk);
+    // The node claims the transaction of the hash references itself as trunk
+    let node = MockNode::start(move |_| (200, json!({ "trytes": [trytes] }).to_string()));
+
+    let err = Client::traverse_bundle(&trunk).await.unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::BundleCycleDetected) => (),
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(
+        node.commands(),
+        vec!["getNodeAPIConfiguration", "getTrytes"]
+    );
+}
+
+#[smol_potat::test]
+async fn test_traverse_bundle_checks_indexes() {
+    let _lock = lock_client();
+    let second = transaction(5, 1, Hash::zeros());
+    let tail = transaction(0, 1, second.0);
+    let tail_hash = tail.0;
+    let node = MockNode::start(tangle(vec![tail, second]));
+
+    assert!(Client::traverse_bundle(&tail_hash).await.is_err());
+    assert_eq!(
+        node.commands(),
+        vec!["getNodeAPIConfiguration", "getTrytes", "getTrytes"]
+    );
+}
+
+#[smol_potat::test]
+async fn test_traverse_bundle_too_long() {
+    let _lock = lock_client();
+    let tail = transaction(0, MAX_BUNDLE_LENGTH, Hash::zeros());
+    let tail_hash = tail.0;
+    let node = MockNode::start(tangle(vec![tail]));
+
+    let err = Client::traverse_bundle(&tail_hash).await.unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::BundleTooLong(max)) => assert_eq!(*max, MAX_BUNDLE_LENGTH),
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(
+        node.commands(),
+        vec!["getNodeAPIConfiguration", "getTrytes"]
+    );
+}
+
+#[smol_potat::test]
+async fn test_set_max_bundle_length() {
+    let _lock = lock_client();
+    let head = transaction(2, 2, Hash::zeros());
+    let middle = transaction(1, 2, head.0);
+    let tail = transaction(0, 2, middle.0);
+    let tail_hash = tail.0;
+    let _node = MockNode::start(tangle(vec![tail, middle, head]));
+
+    Client::set_max_bundle_length(2);
+    let err = Client::traverse_bundle(&tail_hash).await.unwrap_err();
+    Client::set_max_bundle_length(MAX_BUNDLE_LENGTH);
+    match err.downcast_ref::<Error>() {
+        Some(Error::BundleTooLong(max)) => assert_eq!(*max, 2),
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(Client::traverse_bundle(&tail_hash).await.unwrap().len(), 3);
+}
+
+#[smol_potat::test]
+async fn test_traverse_bundle() {
+    let _lock = lock_client();
+    let head = transaction(2, 2, Hash::zeros());
+    let middle = transaction(1, 2, head.0);
+    let tail = transaction(0, 2, middle.0);
+    let tail_hash = tail.0;
+    let _node = MockNode::start(tangle(vec![tail, middle, head]));
+
+    assert_eq!(Client::traverse_bundle(&tail_hash).await.unwrap().len(), 3);
+}
+
+#[smol_potat::test]
+async fn test_get_trytes_as_bundles() {
+    let _lock = lock_client();
+    let complete = hash(TEST_BUNDLE_HASH_0);
+    let complete_head = bundle_transaction(Address::zeros(), complete, 1, 1, Hash::zeros(), 0);
+    let complete_tail = bundle_transaction(Address::zeros(), complete, 0, 1, complete_head.0, 0);
+    let partial = hash(TEST_TRUNK_HASH);
+    let partial_head = bundle_transaction(Address::zeros(), partial, 2, 2, Hash::zeros(), 0);
+    let partial_middle = bundle_transaction(Address::zeros(), partial, 1, 2, partial_head.0, 0);
+    let partial_tail = bundle_transaction(Address::zeros(), partial, 0, 2, partial_middle.0, 0);
+    let hashes = [complete_head.0, partial_tail.0, complete_tail.0];
+    let middle_hash = partial_middle.0;
+    let node = MockNode::start(tangle(vec![
+        complete_head,
+        complete_tail,
+        partial_head,
+        partial_middle,
+        partial_tail,
+    ]));
+
+    let bundles = Client::get_trytes_as_bundles(&hashes).await.unwrap();
+    assert_eq!(bundles.len(), 2);
+    let hashes_of =
+        |bundle: &[Transaction]| bundle.iter().map(transaction_hash).collect::<Vec<_>>();
+    assert_eq!(hashes_of(&bundles[0]), vec![hashes[2], hashes[0]]);
+    assert_eq!(hashes_of(&bundles[1])[..2], [hashes[1], middle_hash]);
+    assert_eq!(bundles[1].len(), 3);
+    // One request for the given hashes, and one per transaction of the partial bundle's walk
+    let get_trytes = node
+        .commands()
+        .iter()
+        .filter(|command| *command == "getTrytes")
+        .count();
+    assert_eq!(get_trytes, 4);
+
+    assert!(Client::get_trytes_as_bundles(&[middle_hash]).await.is_err());
+}
+
+#[smol_potat::test]
+async fn test_get_trytes_as_bundles_reattachments() {
+    let _lock = lock_client();
+    let bundle = hash(TEST_BUNDLE_HASH_0);
+    let head = bundle_transaction(Address::zeros(), bundle, 1, 1, Hash::zeros(), 0);
+    let tail = bundle_transaction(Address::zeros(), bundle, 0, 1, head.0, 0);
+    // A reattachment has the same bundle hash, but different transaction hashes
+    let reattached_head = bundle_transaction(Address::zeros(), bundle, 1, 1, Hash::zeros(), 1);
+    let reattached_tail = bundle_transaction(Address::zeros(), bundle, 0, 1, reattached_head.0, 1);
+    let hashes = [tail.0, head.0, reattached_tail.0];
+    let reattached_head_hash = reattached_head.0;
+    let _node = MockNode::start(tangle(vec![head, tail, reattached_head, reattached_tail]));
+
+    let bundles = Client::get_trytes_as_bundles(&hashes).await.unwrap();
+    let hashes_of =
+        |bundle: &[Transaction]| bundle.iter().map(transaction_hash).collect::<Vec<_>>();
+    assert_eq!(bundles.len(), 2);
+    assert_eq!(hashes_of(&bundles[0]), vec![hashes[0], hashes[1]]);
+    assert_eq!(
+        hashes_of(&bundles[1]),
+        vec![hashes[2], reattached_head_hash]
+    );
+}
+
+#[smol_potat::test]
+async fn test_find_transactions_empty_result() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|_| (200, json!({ "hashes": [] }).to_string()));
+
+    let res = Client::find_transactions()
+        .addresses(&[address(TEST_ADDRESS_0)])
+        .send()
+        .await
+        .unwrap();
+    assert!(res.hashes.is_empty());
+}
+
+#[smol_potat::test]
+async fn test_find_transactions_node_failure() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|_| (500, "{}".to_string()));
+
+    assert!(Client::find_transactions()
+        .addresses(&[address(TEST_ADDRESS_0)])
+        .send()
+        .await
+        .is_err());
+}
+
+#[smol_potat::test]
+async fn test_find_transactions_splits_every_long_field() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getNodeAPIConfiguration" => (
+            200,
+            json!({
+                "maxFindTransactions": 100000,
+                "maxRequestsList": 2,
+                "maxGetTrytes": 1000,
+                "maxBodyLength": 1000000,
+                "testNet": false,
+                "milestoneStartIndex": STARTING_MILESTONE_INDEX,
+            })
+            .to_string(),
+        ),
+        _ => {
+            let len = |field: &str| body[field].as_array().unwrap().len();
+            if len("addresses") > 2 || len("bundles") > 2 {
+                return (400, json!({ "error": "Too many values" }).to_string());
+            }
+            (200, json!({ "hashes": [TEST_TX_HASH] }).to_string())
+        }
+    });
+
+    let addresses = [TEST_ADDRESS_0, TEST_TX_HASH, TEST_BUNDLE_TX_0];
+    let bundles = [TEST_BUNDLE_HASH_0, TEST_TRUNK_HASH, TEST_BRANCH_HASH];
+    let res = Client::find_transactions()
+        .addresses(&addresses.iter().map(|a| address(a)).collect::<Vec<_>>())
+        .bundles(&bundles.iter().map(|b| hash(b)).collect::<Vec<_>>())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.hashes, vec![hash(TEST_TX_HASH)]);
+
+    // Both fields are split in two chunks, and every pair of them is requested
+    let mut pairs = Vec::new();
+    for body in node
+        .requests()
+        .iter()
+        .filter(|body| body["command"] == "findTransactions")
+    {
+        let values = |field: &str| -> Vec<String> {
+            body[field]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|value| value.as_str().unwrap().to_string())
+                .collect()
+        };
+        for address in values("addresses") {
+            for bundle in values("bundles") {
+                pairs.push((address.clone(), bundle));
+            }
+        }
+    }
+    assert_eq!(pairs.len(), addresses.len() * bundles.len());
+    assert_eq!(node.commands().len(), 5);
+}
+
+#[smol_potat::test]
+async fn test_find_transactions_sorted() {
+    let _lock = lock_client();
+    // Every response is rotated by one, like a node returning its hashes in arbitrary order
+    let calls = AtomicUsize::new(0);
+    let _node = MockNode::start(move |_| {
+        let mut hashes = vec![
+            TEST_TX_HASH,
+            TEST_BUNDLE_TX_1,
+            TEST_TRUNK_HASH,
+            TEST_BUNDLE_TX_0,
+        ];
+        hashes.rotate_left(calls.fetch_add(1, Ordering::SeqCst) % 4);
+        (200, json!({ "hashes": hashes }).to_string())
+    });
+
+    let find = || {
+        Client::find_transactions()
+            .addresses(&[address(TEST_ADDRESS_0)])
+            .sorted(true)
+            .send()
+    };
+    let first = find().await.unwrap().hashes;
+    let second = find().await.unwrap().hashes;
+    assert_eq!(first, second);
+    let mut expected = vec![
+        TEST_TX_HASH,
+        TEST_BUNDLE_TX_1,
+        TEST_TRUNK_HASH,
+        TEST_BUNDLE_TX_0,
+    ];
+    expected.sort();
+    assert_eq!(first, expected.into_iter().map(hash).collect::<Vec<_>>());
+}
+
+#[smol_potat::test]
+async fn test_were_addresses_spent_from_batches() {
+    let _lock = lock_client();
+    // Addresses starting with A to M are spent
+    let spent = |address: &str| address.as_bytes()[0] <= b'M';
+    let node = MockNode::start(move |body| {
+        if body["command"] == "getNodeInfo" {
+            return (200, node_info(TEST_MILESTONE_0));
+        }
+        let states: Vec<bool> = body["addresses"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|address| spent(address.as_str().unwrap()))
+            .collect();
+        (200, json!({ "states": states }).to_string())
+    });
+
+    let addresses: Vec<String> = (0..1500)
+        .map(|i| {
+            let name: String = [i % 26, i / 26 % 26, i / 676]
+                .iter()
+                .map(|n| (b'A' + *n as u8) as char)
+                .collect();
+            format!("{:9<81}", name)
+        })
+        .collect();
+    let res = Client::were_addresses_spent_from(
+        &addresses.iter().map(|a| address(a)).collect::<Vec<_>>(),
+    )
+    .await
+    .unwrap();
+
+    let expected: Vec<bool> = addresses.iter().map(|a| spent(a)).collect();
+    assert_eq!(res.states, expected);
+    let mut batches: Vec<usize> = node
+        .requests()
+        .iter()
+        .filter(|body| body["command"] == "wereAddressesSpentFrom")
+        .map(|body| body["addresses"].as_array().unwrap().len())
+        .collect();
+    batches.sort();
+    assert_eq!(batches, vec![500, 1000]);
+}
+
+#[smol_potat::test]
+async fn test_were_addresses_spent_from_cache() {
+    let _lock = lock_client();
+    let spent = address(TEST_ADDRESS_0);
+    let unspent = address(&"A".repeat(81));
+    let node = MockNode::start(move |body| {
+        if body["command"] == "getNodeInfo" {
+            return (200, node_info(TEST_MILESTONE_0));
+        }
+        let states: Vec<bool> = body["addresses"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|address| address == TEST_ADDRESS_0)
+            .collect();
+        (200, json!({ "states": states }).to_string())
+    });
+
+    let res = Client::were_addresses_spent_from(&[spent.clone(), unspent.clone()])
+        .await
+        .unwrap();
+    assert_eq!(res.states, vec![true, false]);
+    assert_eq!(
+        node.commands(),
+        vec!["getNodeInfo", "wereAddressesSpentFrom"]
+    );
+
+    // The spent address is answered from the cache
+    let res = Client::were_addresses_spent_from(std::slice::from_ref(&spent))
+        .await
+        .unwrap();
+    assert_eq!(res.states, vec![true]);
+    assert_eq!(node.requests().len(), 2);
+
+    // Unspent addresses are requested again, without the spent one
+    let res = Client::were_addresses_spent_from(&[unspent, spent])
+        .await
+        .unwrap();
+    assert_eq!(res.states, vec![false, true]);
+    let requests = node.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[2]["addresses"], json!(["A".repeat(81)]));
+}
+
+#[smol_potat::test]
+async fn test_get_balances_threshold() {
+    let _lock = lock_client();
+    let node = MockNode::start(|_| {
+        (
+            200,
+            json!({ "balances": ["10"], "milestoneIndex": 1, "references": [TEST_MILESTONE_0] })
+                .to_string(),
+        )
+    });
+
+    assert!(Client::get_balances()
+        .addresses(&[address(TEST_ADDRESS_0)])
+        .threshold(101)
+        .send()
+        .await
+        .is_err());
+    assert!(node.requests().is_empty());
+
+    let res = Client::get_balances()
+        .addresses(&[address(TEST_ADDRESS_0)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.balances, vec![10]);
+    let requests = node.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["threshold"], 100);
+}
+
+#[smol_potat::test]
+async fn test_get_balances_without_addresses() {
+    let _lock = lock_client();
+    let node = MockNode::start(|_| (500, json!({ "error": "unexpected request" }).to_string()));
+
+    let res = Client::get_balances().addresses(&[]).send().await.unwrap();
+    assert!(res.balances.is_empty());
+    assert!(res.references.is_empty());
+    assert!(node.requests().is_empty());
+}
+
+#[test]
+fn test_add_node_from_env() {
+    let _lock = lock_client();
+    let var = "IOTA_CLIENT_TEST_NODE";
+
+    std::env::remove_var(var);
+    let err = Client::add_node_from_env(var).unwrap_err().to_string();
+    assert!(err.contains(var), "{}", err);
+
+    std::env::set_var(var, "http://127.0.0.1:14265");
+    assert!(Client::add_node_from_env(var).unwrap());
+    assert!(Client::remove_node("http://127.0.0.1:14265").unwrap());
+
+    for invalid in &["127.0.0.1:14265", "tcp://127.0.0.1:15600"] {
+        std::env::set_var(var, invalid);
+        let err = Client::add_node_from_env(var).unwrap_err().to_string();
+        assert!(err.contains("Invalid node URL"), "{}", err);
+    }
+    std::env::remove_var(var);
+}
+
+#[smol_potat::test]
+async fn test_get_transfers() {
+    let _lock = lock_client();
+    let seed = seed();
+    let addresses: Vec<Address> = (0..2)
+        .map(|index| seed_address(index, WotsSecurityLevel::Medium))
+        .collect();
+    // The later bundle touches the first address, the earlier one both addresses
+    let later = hash(TEST_BUNDLE_HASH_0);
+    let later_tail = bundle_transaction(addresses[0].clone(), later, 0, 0, Hash::zeros(), 2000);
+    let earlier = hash(TEST_TRUNK_HASH);
+    let earlier_head = bundle_transaction(addresses[1].clone(), earlier, 1, 1, Hash::zeros(), 1000);
+    let earlier_tail =
+        bundle_transaction(addresses[0].clone(), earlier, 0, 1, earlier_head.0, 1000);
+    let (later_hash, earlier_hash) = (later_tail.0, earlier_tail.0);
+    let _node = MockNode::start(tangle(vec![later_tail, earlier_head, earlier_tail]));
+
+    let transfers = Client::get_transfers(&seed).send().await.unwrap();
+    assert_eq!(transfers.len(), 2);
+    assert_eq!(transfers[0].len(), 2);
+    assert_eq!(transaction_hash(&transfers[0][0]), earlier_hash);
+    assert_eq!(transfers[1].len(), 1);
+    assert_eq!(transaction_hash(&transfers[1][0]), later_hash);
+}
+
+#[smol_potat::test]
+async fn test_get_transfers_page() {
+    let _lock = lock_client();
+    let seed = seed();
+    let address = seed_address(0, WotsSecurityLevel::Medium);
+    let bundles = [TEST_BUNDLE_HASH_0, TEST_TRUNK_HASH, TEST_BRANCH_HASH];
+    let tails: Vec<(Hash, String)> = bundles
+        .iter()
+        .zip(&[3000, 1000, 2000])
+        .map(|(bundle, ts)| {
+            bundle_transaction(address.clone(), hash(bundle), 0, 0, Hash::zeros(), *ts)
+        })
+        .collect();
+    let second = tails[2].0;
+    let node = MockNode::start(tangle(tails));
+
+    let transfers = Client::get_transfers(&seed)
+        .query(TransferQuery { start: 1, limit: 1 })
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].len(), 1);
+    assert_eq!(transaction_hash(&transfers[0][0]), second);
+    // Only the tail of the page is traversed
+    let traversed = node
+        .requests()
+        .iter()
+        .filter(|body| {
+            body["command"] == "getTrytes" && body["hashes"].as_array().unwrap().len() == 1
+        })
+        .count();
+    assert_eq!(traversed, 1);
+}
+
+#[smol_potat::test]
+async fn test_get_new_address_with_checksum() {
+    let _lock = lock_client();
+    let seed = seed();
+    let _node = MockNode::start(tangle(Vec::new()));
+
+    let (index, address) = Client::get_new_address(&seed)
+        .generate_with_checksum()
+        .await
+        .unwrap();
+    let bare = seed_address(0, WotsSecurityLevel::Medium);
+    assert_eq!(index, 0);
+    assert_eq!(address.len(), 90);
+    assert_eq!(
+        &address[..81],
+        bare.to_inner().as_i8_slice().trytes().unwrap()
+    );
+    assert_eq!(address, add_checksum(&bare));
+}
+
+#[smol_potat::test]
+async fn test_get_trytes_uses_max_get_trytes() {
+    let _lock = lock_client();
+    let transactions: Vec<_> = (0..5).map(|i| transaction(i, 4, Hash::zeros())).collect();
+    let hashes: Vec<Hash> = transactions.iter().map(|(hash, _)| *hash).collect();
+    let tangle = tangle(transactions);
+    let node = MockNode::start(move |body| match body["command"].as_str() {
+        Some("getNodeAPIConfiguration") => (
+            200,
+            json!({
+                "maxFindTransactions": 100000,
+                "maxRequestsList": 1000,
+                "maxGetTrytes": 2,
+                "maxBodyLength": 1000000,
+                "testNet": false,
+                "milestoneStartIndex": STARTING_MILESTONE_INDEX,
+                "mwm": 14,
+            })
+            .to_string(),
+        ),
+        _ => tangle(body),
+    });
+
+    let config = Client::get_node_api_configuration().await.unwrap();
+    assert_eq!(config.max_get_trytes, Some(2));
+    assert_eq!(config.max_find_transactions, Some(100000));
+    assert_eq!(
+        config.milestone_start_index,
+        STARTING_MILESTONE_INDEX as i64
+    );
+    assert_eq!(config.mwm, Some(14));
+
+    let trytes = Client::get_trytes(&hashes).await.unwrap().trytes;
+    let fetched: Vec<Hash> = trytes
+        .iter()
+        .map(|tx| transaction_hash(tx.as_ref().unwrap()))
+        .collect();
+    assert_eq!(fetched, hashes);
+    // Chunks are sent concurrently, so they may arrive in any order
+    let mut chunks: Vec<usize> = node
+        .requests()
+        .iter()
+        .filter(|body| body["command"] == "getTrytes")
+        .map(|body| body["hashes"].as_array().unwrap().len())
+        .collect();
+    chunks.sort();
+    assert_eq!(chunks, vec![1, 2, 2]);
+}
+
+#[smol_potat::test]
+async fn test_get_trytes_stream() {
+    let _lock = lock_client();
+    let transactions: Vec<_> = (0..7).map(|i| transaction(i, 6, Hash::zeros())).collect();
+    let hashes: Vec<Hash> = transactions.iter().map(|(hash, _)| *hash).collect();
+    let tangle = tangle(transactions);
+    let node = MockNode::start(move |body| match body["command"].as_str() {
+        Some("getNodeAPIConfiguration") => (
+            200,
+            json!({
+                "maxFindTransactions": 100000,
+                "maxRequestsList": 1000,
+                "maxGetTrytes": 2,
+                "maxBodyLength": 1000000,
+                "testNet": false,
+                "milestoneStartIndex": STARTING_MILESTONE_INDEX,
+            })
+            .to_string(),
+        ),
+        _ => tangle(body),
+    });
+
+    let streamed: Vec<Option<Transaction>> = Client::get_trytes_stream(hashes.clone())
+        .try_collect()
+        .await
+        .unwrap();
+    let collected = Client::get_trytes(&hashes).await.unwrap().trytes;
+    assert_eq!(streamed.len(), 7);
+    let streamed: Vec<Hash> = streamed
+        .iter()
+        .map(|tx| transaction_hash(tx.as_ref().unwrap()))
+        .collect();
+    let collected: Vec<Hash> = collected
+        .iter()
+        .map(|tx| transaction_hash(tx.as_ref().unwrap()))
+        .collect();
+    assert_eq!(streamed, hashes);
+    assert_eq!(streamed, collected);
+
+    let mut chunks: Vec<usize> = node
+        .requests()
+        .iter()
+        .filter(|body| body["command"] == "getTrytes")
+        .map(|body| body["hashes"].as_array().unwrap().len())
+        .collect();
+    chunks.sort();
+    assert_eq!(chunks, vec![1, 1, 2, 2, 2, 2, 2, 2]);
+}
+
+#[smol_potat::test]
+async fn test_prepare_transfers_skips_spent_remainder() {
+    let _lock = lock_client();
+    let spent = seed_address(1, WotsSecurityLevel::Low)
+        .to_inner()
+        .as_i8_slice()
+        .trytes()
+        .unwrap();
+    let node = MockNode::start(move |body| {
+        if body["command"] == "getNodeInfo" {
+            return (200, node_info(TEST_MILESTONE_0));
+        }
+        let states: Vec<bool> = body["addresses"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|address| address.as_str().unwrap() == spent)
+            .collect();
+        (200, json!({ "states": states }).to_string())
+    });
+
+    let bundle = Client::prepare_transfers(&seed())
+        .transfers(vec![Transfer {
+            address: address(TEST_ADDRESS_0),
+            value: 3,
+            message: None,
+            tag: None,
+        }])
+        .security(1)
+        .inputs(vec![Input::new(address(TEST_TRUNK_HASH), 10, 0)])
+        .build()
+        .await
+        .unwrap();
+
+    let remainder = bundle
+        .into_iter()
+        .find(|tx| *tx.value().to_inner() == 7)
+        .unwrap();
+    assert_eq!(
+        remainder.address(),
+        &seed_address(2, WotsSecurityLevel::Low)
+    );
+    assert_eq!(
+        node.commands(),
+        vec![
+            "getNodeInfo",
+            "wereAddressesSpentFrom",
+            "wereAddressesSpentFrom"
+        ]
+    );
+}
+
+#[smol_potat::test]
+async fn test_get_bundle_value_sum() {
+    let _lock = lock_client();
+    let head = value_transaction(1, 1, Hash::zeros(), -5);
+    let tail = value_transaction(0, 1, head.0, 5);
+    let tail_hash = tail.0;
+    let _node = MockNode::start(tangle(vec![tail, head]));
+
+    assert_eq!(Client::get_bundle(&tail_hash).await.unwrap().len(), 2);
+
+    let head = value_transaction(1, 1, Hash::zeros(), -5);
+    let tail = value_transaction(0, 1, head.0, 6);
+    let tail_hash = tail.0;
+    let _node = MockNode::start(tangle(vec![tail, head]));
+
+    assert!(Client::get_bundle(&tail_hash).await.is_err());
+}
+
+#[smol_potat::test]
+async fn test_get_trytes_retries_unknown_transactions() {
+    let _lock = lock_client();
+    let (tx_hash, trytes) = transaction(0, 0, Hash::zeros());
+    let other_tangle = tangle(vec![(tx_hash, trytes)]);
+    let other = MockNode::start(move |body| match body["command"].as_str() {
+        Some("getNodeInfo") => (500, "{}".to_string()),
+        _ => other_tangle(body),
+    });
+    // The failure makes the client ask the first node before the other one
+    assert!(Client::get_node_info().await.is_err());
+    let first = MockNode::start(tangle(vec![]));
+
+    let trytes = Client::get_trytes(&[tx_hash]).await.unwrap().trytes;
+    assert!(trytes[0].is_none());
+    assert!(!other.commands().contains(&"getTrytes".to_string()));
+
+    Client::set_trytes_retries(1);
+    let trytes = Client::get_trytes(&[tx_hash]).await.unwrap().trytes;
+    Client::set_trytes_retries(0);
+    assert_eq!(transaction_hash(trytes[0].as_ref().unwrap()), tx_hash);
+    assert_eq!(
+        first
+            .commands()
+            .iter()
+            .filter(|command| *command == "getTrytes")
+            .count(),
+        2
+    );
+}
+
+#[smol_potat::test]
+async fn test_send_transfers_retries_inconsistent_tips() {
+    let _lock = lock_client();
+    let tips = AtomicUsize::new(0);
+    let node = MockNode::start(move |body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => (200, node_info(TEST_MILESTONE_0)),
+        "getTransactionsToApprove" => {
+            // The first tips are inconsistent, the second ones consistent
+            let trunk = match tips.fetch_add(1, Ordering::SeqCst) {
+                0 => TEST_TRUNK_HASH,
+                _ => TEST_TX_HASH,
+            };
+            let tips = json!({ "trunkTransaction": trunk, "branchTransaction": TEST_BRANCH_HASH });
+            (200, tips.to_string())
+        }
+        "checkConsistency" => {
+            let state = body["tails"][0] != TEST_TRUNK_HASH;
+            (200, json!({ "state": state }).to_string())
+        }
+        "attachToTangle" => (200, json!({ "trytes": body["trytes"] }).to_string()),
+        _ => (200, "{}".to_string()),
+    });
+
+    let transfer = Transfer {
+        address: address(TEST_ADDRESS_0),
+        value: 0,
+        message: None,
+        tag: None,
+    };
+    Client::send_transfers(&seed())
+        .transfers(vec![transfer])
+        .send()
+        .await
+        .unwrap();
+
+    let requests = node.requests();
+    let attach = requests
+        .iter()
+        .find(|body| body["command"] == "attachToTangle")
+        .unwrap();
+    assert_eq!(attach["trunkTransaction"], TEST_TX_HASH);
+    assert_eq!(
+        node.commands()
+            .iter()
+            .filter(|command| *command == "checkConsistency")
+            .count(),
+        2
+    );
+}
+
+#[smol_potat::test]
+async fn test_send_transfers_insufficient_balance() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getBalances" => {
+            let balances = json!({
+                "balances": ["3", "4"],
+                "milestoneIndex": 1,
+                "references": [TEST_MILESTONE_0],
+            });
+            (200, balances.to_string())
+        }
+        command => panic!("unexpected command {}", command),
+    });
+
+    let seed = seed();
+    // The inputs claim enough balance, but the node knows better
+    let err = Client::send_transfers(&seed)
+        .transfers(vec![Transfer {
+            address: address(TEST_ADDRESS_0),
+            value: 10,
+            message: None,
+            tag: None,
+        }])
+        .inputs(vec![
+            Input::new(seed_address(0, WotsSecurityLevel::Medium), 5, 0),
+            Input::new(seed_address(1, WotsSecurityLevel::Medium), 5, 1),
+        ])
+        .send()
+        .await
+        .unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::InsufficientBalance { needed, available }) => {
+            assert_eq!((*needed, *available), (10, 7))
+        }
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(node.commands(), vec!["getBalances"]);
+}
+
+#[smol_potat::test]
+async fn test_send_transfers_to_several_addresses() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => (200, node_info(TEST_MILESTONE_0)),
+        "getBalances" => {
+            let balances = json!({
+                "balances": ["10"],
+                "milestoneIndex": 1,
+                "references": [TEST_MILESTONE_0],
+            });
+            (200, balances.to_string())
+        }
+        "getTransactionsToApprove" => (
+            200,
+            json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH })
+                .to_string(),
+        ),
+        "checkConsistency" => (200, json!({ "state": true }).to_string()),
+        "attachToTangle" => (200, json!({ "trytes": body["trytes"] }).to_string()),
+        _ => (200, json!({}).to_string()),
+    });
+
+    let seed = seed();
+    let input = seed_address(0, WotsSecurityLevel::Medium);
+    let outputs = [TEST_ADDRESS_0, TEST_TRUNK_HASH, TEST_BRANCH_HASH];
+    let transfers = outputs
+        .iter()
+        .zip(1..)
+        .map(|(output, value)| Transfer {
+            address: address(output),
+            value,
+            message: None,
+            tag: None,
+        })
+        .collect();
+    let bundle = Client::send_transfers(&seed)
+        .transfers(transfers)
+        .inputs(vec![Input::new(input.clone(), 10, 0)])
+        .remainder(address(TEST_BUNDLE_HASH_0))
+        .send()
+        .await
+        .unwrap();
+
+    let mut values: Vec<(String, i64)> = bundle
+        .iter()
+        .map(|tx| {
+            (
+                tx.address().to_inner().as_i8_slice().trytes().unwrap(),
+                *tx.value().to_inner(),
+            )
+        })
+        .collect();
+    values.sort();
+    let input = input.to_inner().as_i8_slice().trytes().unwrap();
+    let mut expected = vec![
+        (TEST_ADDRESS_0.to_string(), 1),
+        (TEST_TRUNK_HASH.to_string(), 2),
+        (TEST_BRANCH_HASH.to_string(), 3),
+        (input.clone(), -10),
+        // The second signature fragment of security level 2
+        (input, 0),
+        (TEST_BUNDLE_HASH_0.to_string(), 4),
+    ];
+    expected.sort();
+    assert_eq!(values, expected);
+    assert!(bundle.iter().all(|tx| tx.bundle() == bundle[0].bundle()));
+    let commands = node.commands();
+    for command in &[
+        "attachToTangle",
+        "storeTransactions",
+        "broadcastTransactions",
+    ] {
+        assert_eq!(commands.iter().filter(|c| c == command).count(), 1);
+    }
+}
+
+#[smol_potat::test]
+async fn test_send_transfers_dust_threshold() {
+    let _lock = lock_client();
+    let input = seed_address(0, WotsSecurityLevel::Medium);
+    let input_trytes = input.to_inner().as_i8_slice().trytes().unwrap();
+    let node = MockNode::start(move |body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => (200, node_info(TEST_MILESTONE_0)),
+        "getBalances" => {
+            let balances: Vec<String> = body["addresses"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|address| match address.as_str().unwrap() {
+                    a if a == input_trytes => "10".to_string(),
+                    TEST_TRUNK_HASH => "1000000".to_string(),
+                    _ => "0".to_string(),
+                })
+                .collect();
+            let balances = json!({
+                "balances": balances,
+                "milestoneIndex": 1,
+                "references": [TEST_MILESTONE_0],
+            });
+            (200, balances.to_string())
+        }
+        "getTransactionsToApprove" => (
+            200,
+            json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH })
+                .to_string(),
+        ),
+        "checkConsistency" => (200, json!({ "state": true }).to_string()),
+        "attachToTangle" => (200, json!({ "trytes": body["trytes"] }).to_string()),
+        _ => (200, json!({}).to_string()),
+    });
+
+    let seed = seed();
+    let send = |output: &str| {
+        Client::send_transfers(&seed)
+            .transfers(vec![Transfer {
+                address: address(output),
+                value: 1,
+                message: None,
+                tag: None,
+            }])
+            .inputs(vec![Input::new(input.clone(), 10, 0)])
+            .remainder(address(TEST_BUNDLE_HASH_0))
+            .dust_threshold(1_000_000)
+            .send()
+    };
+
+    let err = send(TEST_ADDRESS_0).await.unwrap_err();
+    match err.downcast_ref::<Error>() {
+        Some(Error::DustOutput { address, value }) => {
+            assert_eq!((address.as_str(), *value), (TEST_ADDRESS_0, 1))
+        }
+        _ => panic!("unexpected error: {}", err),
+    }
+    assert_eq!(node.commands(), vec!["getBalances", "getBalances"]);
+
+    let bundle = send(TEST_TRUNK_HASH).await.unwrap();
+    assert!(bundle.iter().any(|tx| *tx.value().to_inner() == 1));
+    let commands = node.commands();
+    assert_eq!(
+        commands.iter().filter(|c| *c == "attachToTangle").count(),
+        1
+    );
+}
+
+#[smol_potat::test]
+async fn test_get_address_states() {
+    let _lock = lock_client();
+    let addresses = [TEST_ADDRESS_0, TEST_TRUNK_HASH, TEST_BRANCH_HASH];
+    let node = MockNode::start(move |body| {
+        let requested: Vec<&str> = body["addresses"]
+            .as_array()
+            .map(|a| a.iter().map(|a| a.as_str().unwrap()).collect())
+            .unwrap_or_default();
+        match body["command"].as_str().unwrap() {
+            "getBalances" => {
+                let balances: Vec<String> = requested
+                    .iter()
+                    .map(|a| (addresses.iter().position(|x| x == a).unwrap() * 10).to_string())
+                    .collect();
+                let balances = json!({
+                    "balances": balances,
+                    "milestoneIndex": 1,
+                    "references": [TEST_MILESTONE_0],
+                });
+                (200, balances.to_string())
+            }
+            "wereAddressesSpentFrom" => {
+                let states: Vec<bool> = requested.iter().map(|a| *a == TEST_TRUNK_HASH).collect();
+                (200, json!({ "states": states }).to_string())
+            }
+            _ => (200, json!({}).to_string()),
+        }
+    });
+
+    let states =
+        Client::get_address_states(&addresses.iter().map(|a| address(a)).collect::<Vec<_>>())
+            .await
+            .unwrap();
+    let states: Vec<(String, u64, bool)> = states
+        .iter()
+        .map(|state| {
+            (
+                state.address.to_inner().as_i8_slice().trytes().unwrap(),
+                state.balance,
+                state.spent,
+            )
+        })
+        .collect();
+    assert_eq!(
+        states,
+        vec![
+            (TEST_ADDRESS_0.to_string(), 0, false),
+            (TEST_TRUNK_HASH.to_string(), 10, true),
+            (TEST_BRANCH_HASH.to_string(), 20, false),
+        ]
+    );
+    let mut commands = node.commands();
+    commands.retain(|c| c != "getNodeInfo");
+    commands.sort();
+    assert_eq!(commands, vec!["getBalances", "wereAddressesSpentFrom"]);
+}
+
+#[smol_potat::test]
+async fn test_get_address_states_in_chunks() {
+    let _lock = lock_client();
+    // The key index of each address is encoded in its first trytes
+    let index = |trytes: &str| {
+        trytes_to_indices(&trytes[..3])
+            .unwrap()
+            .iter()
+            .rev()
+            .fold(0, |acc, i| acc * 27 + *i as usize)
+    };
+    let node = MockNode::start(move |body| {
+        let requested: Vec<usize> = body["addresses"]
+            .as_array()
+            .map(|a| a.iter().map(|a| index(a.as_str().unwrap())).collect())
+            .unwrap_or_default();
+        match body["command"].as_str().unwrap() {
+            "getBalances" => {
+                let balances: Vec<String> = requested.iter().map(|i| i.to_string()).collect();
+                let balances = json!({
+                    "balances": balances,
+                    "milestoneIndex": 1,
+                    "references": [TEST_MILESTONE_0],
+                });
+                (200, balances.to_string())
+            }
+            "wereAddressesSpentFrom" => {
+                let states: Vec<bool> = requested.iter().map(|i| i % 3 == 0).collect();
+                (200, json!({ "states": states }).to_string())
+            }
+            _ => (200, json!({}).to_string()),
+        }
+    });
+
+    let addresses: Vec<Address> = (0..1001usize)
+        .map(|i| {
+            let trytes =
+                indices_to_trytes(&[(i % 27) as u8, (i / 27 % 27) as u8, (i / 729) as u8]).unwrap();
+            address(&format!("{:9<81}", trytes))
+        })
+        .collect();
+    let states = Client::get_address_states(&addresses).await.unwrap();
+    assert_eq!(states.len(), 1001);
+    for (i, state) in states.iter().enumerate() {
+        assert_eq!(state.address, addresses[i]);
+        assert_eq!((state.balance, state.spent), (i as u64, i % 3 == 0));
+    }
+
+    for command in &["getBalances", "wereAddressesSpentFrom"] {
+        let mut chunks: Vec<usize> = node
+            .requests()
+            .iter()
+            .filter(|body| body["command"] == *command)
+            .map(|body| body["addresses"].as_array().unwrap().len())
+            .collect();
+        chunks.sort();
+        assert_eq!(chunks, vec![1, 1000], "{}", command);
+    }
+}
+
+#[smol_potat::test]
+async fn test_get_address_states_missing_balances() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getBalances" => (
+            200,
+            json!({
+                "balances": ["1"],
+                "milestoneIndex": 1,
+                "references": [TEST_MILESTONE_0],
+            })
+            .to_string(),
+        ),
+        "wereAddressesSpentFrom" => (200, json!({ "states": [false, false] }).to_string()),
+        _ => (200, json!({}).to_string()),
+    });
+
+    let addresses = [address(TEST_ADDRESS_0), address(TEST_TRUNK_HASH)];
+    assert!(Client::get_address_states(&addresses).await.is_err());
+}
+
+#[smol_potat::test]
+async fn test_send_trytes_inconsistent_tips() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getTransactionsToApprove" => (
+            200,
+            json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH })
+                .to_string(),
+        ),
+        _ => (200, json!({ "state": false }).to_string()),
+    });
+
+    let (_, trytes) = transaction(0, 0, Hash::zeros());
+    let tx = Transaction::from_trits(TryteBuf::try_from_str(&trytes).unwrap().as_trits()).unwrap();
+    let err = Client::send_trytes()
+        .trytes(vec![tx])
+        .tip_retries(1)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::CouldNotGetConsistentTips(2))
+    ));
+    assert_eq!(
+        node.commands(),
+        vec![
+            "getTransactionsToApprove",
+            "checkConsistency",
+            "getTransactionsToApprove",
+            "checkConsistency"
+        ]
+    );
+}
+
+#[smol_potat::test]
+async fn test_send_trytes_sends_bundle_once() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => (200, node_info(TEST_MILESTONE_0)),
+        "getTransactionsToApprove" => (
+            200,
+            json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH })
+                .to_string(),
+        ),
+        "checkConsistency" => (200, json!({ "state": true }).to_string()),
+        "attachToTangle" => (200, json!({ "trytes": body["trytes"] }).to_string()),
+        _ => (200, json!({}).to_string()),
+    });
+
+    let (_, trytes) = transaction(0, 0, Hash::zeros());
+    let tx =
+        || Transaction::from_trits(TryteBuf::try_from_str(&trytes).unwrap().as_trits()).unwrap();
+    let first = Client::send_trytes()
+        .trytes(vec![tx()])
+        .send()
+        .await
+        .unwrap();
+    let second = Client::send_trytes()
+        .trytes(vec![tx()])
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        node.commands()
+            .iter()
+            .filter(|command| *command == "storeTransactions")
+            .count(),
+        1
+    );
+    assert_eq!(node.commands().last().unwrap(), "broadcastTransactions");
+    assert_eq!(second.len(), 1);
+    assert_eq!(
+        transaction_trytes(&second[0]),
+        transaction_trytes(&first[0])
+    );
+}
+
+#[smol_potat::test]
+async fn test_send_trytes_concurrently() {
+    let _lock = lock_client();
+    // The first attachment fails, like a network error
+    let attachments = AtomicUsize::new(0);
+    let node = MockNode::start(move |body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => (200, node_info(TEST_MILESTONE_0)),
+        "getTransactionsToApprove" => (
+            200,
+            json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH })
+                .to_string(),
+        ),
+        "checkConsistency" => (200, json!({ "state": true }).to_string()),
+        "attachToTangle" if attachments.fetch_add(1, Ordering::SeqCst) == 0 => {
+            (500, json!({ "error": "Node is down" }).to_string())
+        }
+        "attachToTangle" => (200, json!({ "trytes": body["trytes"] }).to_string()),
+        _ => (200, json!({}).to_string()),
+    });
+
+    let (_, trytes) = transaction(0, 0, Hash::zeros());
+    let send = || {
+        Client::send_trytes()
+            .trytes(vec![Transaction::from_trits(
+                TryteBuf::try_from_str(&trytes).unwrap().as_trits(),
+            )
+            .unwrap()])
+            .send()
+    };
+    // A failed send releases the bundle again
+    assert!(send().await.is_err());
+
+    let (first, second) = futures::join!(send(), send());
+    assert!(first.is_ok());
+    match second.unwrap_err().downcast_ref::<Error>() {
+        Some(Error::BundleInFlight(bundle)) => {
+            assert_eq!(bundle, &Hash::zeros().as_bytes().trytes().unwrap())
+        }
+        err => panic!("unexpected error: {:?}", err),
+    }
+    let count = |command: &str| node.commands().iter().filter(|c| *c == command).count();
+    assert_eq!(count("attachToTangle"), 2);
+    assert_eq!(count("storeTransactions"), 1);
+}
+
+#[smol_potat::test]
+async fn test_send_trytes_forgets_oldest_bundle() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => (200, node_info(TEST_MILESTONE_0)),
+        "getTransactionsToApprove" => (
+            200,
+            json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH })
+                .to_string(),
+        ),
+        "checkConsistency" => (200, json!({ "state": true }).to_string()),
+        "attachToTangle" => (200, json!({ "trytes": body["trytes"] }).to_string()),
+        _ => (200, json!({}).to_string()),
+    });
+    Client::set_max_sent_bundles(1);
+
+    let tx = |bundle: &str| {
+        let (_, trytes) =
+            bundle_transaction(Address::zeros(), hash(bundle), 0, 0, Hash::zeros(), 0);
+        Transaction::from_trits(TryteBuf::try_from_str(&trytes).unwrap().as_trits()).unwrap()
+    };
+    let stored = || {
+        node.commands()
+            .iter()
+            .filter(|command| *command == "storeTransactions")
+            .count()
+    };
+    for bundle in &[TEST_BUNDLE_HASH_0, TEST_TRUNK_HASH, TEST_TRUNK_HASH] {
+        Client::send_trytes()
+            .trytes(vec![tx(bundle)])
+            .send()
+            .await
+            .unwrap();
+    }
+    // The second bundle was remembered, the first one forgotten
+    assert_eq!(stored(), 2);
+
+    Client::send_trytes()
+        .trytes(vec![tx(TEST_BUNDLE_HASH_0)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stored(), 3);
+}
+
+#[smol_potat::test]
+async fn test_get_confirmation_confidence() {
+    let _lock = lock_client();
+    // TEST_BUNDLE_TX_0 is confirmed, TEST_BUNDLE_TX_1 is referenced by all tips, TEST_TX_HASH only
+    // by trunk tips and TEST_BUNDLE_HASH_0 by none
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => (200, node_info(TEST_MILESTONE_0)),
+        "getTransactionsToApprove" => {
+            let tips = json!({
+                "trunkTransaction": TEST_TRUNK_HASH,
+                "branchTransaction": TEST_BRANCH_HASH,
+            });
+            (200, tips.to_string())
+        }
+        "getInclusionStates" => {
+            let tip = body["tips"][0].as_str().unwrap();
+            let referenced = |tx: &str| match tip {
+                TEST_MILESTONE_0 => tx == TEST_BUNDLE_TX_0,
+                TEST_TRUNK_HASH => tx == TEST_BUNDLE_TX_1 || tx == TEST_TX_HASH,
+                _ => tx == TEST_BUNDLE_TX_1,
+            };
+            let states: Vec<bool> = body["transactions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tx| referenced(tx.as_str().unwrap()))
+                .collect();
+            (200, json!({ "states": states }).to_string())
+        }
+        command => panic!("unexpected command {}", command),
+    });
+
+    let confidence = Client::get_confirmation_confidence(&[
+        hash(TEST_BUNDLE_TX_0),
+        hash(TEST_BUNDLE_TX_1),
+        hash(TEST_TX_HASH),
+        hash(TEST_BUNDLE_HASH_0),
+    ])
+    .await
+    .unwrap();
+    assert_eq!(confidence, vec![1.0, 1.0, 0.5, 0.0]);
+    let commands = node.commands();
+    let count = |command: &str| commands.iter().filter(|c| *c == command).count();
+    assert_eq!(count("getTransactionsToApprove"), CONFIDENCE_SAMPLES);
+    assert_eq!(count("getInclusionStates"), 1 + CONFIDENCE_SAMPLES * 2);
+}
+
+#[smol_potat::test]
+async fn test_broadcast_transactions_chunks() {
+    let _lock = lock_client();
+    let node = MockNode::start(|_| (200, "{}".to_string()));
+    // In broadcast order, from the last transaction of the bundle to the tail
+    let bundle: Vec<Transaction> = (0..50)
+        .rev()
+        .map(|index| {
+            transaction_builder(Address::zeros(), Hash::zeros(), index, 49, Hash::zeros(), 0)
+                .build()
+                .unwrap()
+        })
+        .collect();
+    Client::set_broadcast_chunk_size(20);
+    let res = Client::broadcast_transactions(&bundle).await;
+    Client::set_broadcast_chunk_size(DEFAULT_BROADCAST_CHUNK_SIZE);
+    res.unwrap();
+
+    let requests = node.requests();
+    let mut sizes: Vec<usize> = requests
+        .iter()
+        .map(|body| body["trytes"].as_array().unwrap().len())
+        .collect();
+    // The chunk with the tail is sent last
+    assert_eq!(sizes.pop(), Some(10));
+    assert_eq!(sizes, vec![20, 20]);
+    let last = requests.last().unwrap()["trytes"].as_array().unwrap();
+    assert_eq!(last[9], transaction_trytes(&bundle[49]));
+
+    let mut sent: Vec<String> = requests
+        .iter()
+        .flat_map(|body| body["trytes"].as_array().unwrap().clone())
+        .map(|trytes| trytes.as_str().unwrap().to_string())
+        .collect();
+    sent.sort();
+    let mut expected: Vec<String> = bundle.iter().map(transaction_trytes).collect();
+    expected.sort();
+    assert_eq!(sent, expected);
+}
+
+#[smol_potat::test]
+async fn test_broadcast_transactions_duplicate() {
+    let _lock = lock_client();
+    let error = AtomicUsize::new(0);
+    let _node = MockNode::start(move |_| {
+        let error = match error.fetch_add(1, Ordering::SeqCst) {
+            0 => "Duplicate transaction",
+            _ => "Invalid transaction trytes",
+        };
+        (400, json!({ "error": error }).to_string())
+    });
+
+    let (_, trytes) = transaction(0, 0, Hash::zeros());
+    let tx =
+        || Transaction::from_trits(TryteBuf::try_from_str(&trytes).unwrap().as_trits()).unwrap();
+    Client::broadcast_transactions(&[tx()]).await.unwrap();
+    let err = Client::broadcast_transactions(&[tx()]).await.unwrap_err();
+    assert_eq!(err.to_string(), "Invalid transaction trytes");
+}
+
+#[smol_potat::test]
+async fn test_get_transactions_to_approve_cache() {
+    let _lock = lock_client();
+    let node = MockNode::start(|_| {
+        let tips =
+            json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH });
+        (200, tips.to_string())
+    });
+    Client::set_tips_cache_ttl(Duration::from_millis(300));
+
+    for _ in 0..2 {
+        let tips = Client::get_transactions_to_approve()
+            .depth(3)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(tips.trunk_transaction, hash(TEST_TRUNK_HASH));
+    }
+    let cached = node.requests().len();
+    thread::sleep(Duration::from_millis(400));
+    Client::get_transactions_to_approve()
+        .depth(3)
+        .send()
+        .await
+        .unwrap();
+    let expired = node.requests().len();
+    Client::get_transactions_to_approve()
+        .depth(3)
+        .fresh(true)
+        .send()
+        .await
+        .unwrap();
+    let fresh = node.requests().len();
+    Client::set_tips_cache_ttl(Duration::from_secs(0));
+
+    assert_eq!((cached, expired, fresh), (1, 2, 3));
+}
+
+#[smol_potat::test]
+async fn test_get_transactions_to_approve_depth() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        // The node is 5 milestones behind
+        "getNodeInfo" => {
+            let mut info: serde_json::Value =
+                serde_json::from_str(&node_info(TEST_MILESTONE_0)).unwrap();
+            info["latestSolidSubtangleMilestoneIndex"] = json!(STARTING_MILESTONE_INDEX - 4);
+            (200, info.to_string())
+        }
+        _ => {
+            let tips = json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH });
+            (200, tips.to_string())
+        }
+    });
+
+    for depth in &[Depth::Auto, Depth::Fixed(5), 7.into()] {
+        Client::get_transactions_to_approve()
+            .depth(*depth)
+            .send()
+            .await
+            .unwrap();
+    }
+
+    let depths: Vec<u64> = node
+        .requests()
+        .iter()
+        .filter(|body| body["command"] == "getTransactionsToApprove")
+        .map(|body| body["depth"].as_u64().unwrap())
+        .collect();
+    assert_eq!(depths, vec![u64::from(DEFAULT_DEPTH) + 5, 5, 7]);
+    assert_eq!(
+        node.commands()
+            .iter()
+            .filter(|c| *c == "getNodeInfo")
+            .count(),
+        1
+    );
+}
+
+#[smol_potat::test]
+async fn test_auto_depth_with_huge_lag() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => {
+            let mut info: serde_json::Value =
+                serde_json::from_str(&node_info(TEST_MILESTONE_0)).unwrap();
+            info["latestMilestoneIndex"] = json!(u32::MAX);
+            info["latestSolidSubtangleMilestoneIndex"] = json!(0);
+            (200, info.to_string())
+        }
+        _ => {
+            let tips = json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH });
+            (200, tips.to_string())
+        }
+    });
+
+    Client::get_transactions_to_approve()
+        .depth(Depth::Auto)
+        .send()
+        .await
+        .unwrap();
+    let depth = node.requests().last().unwrap()["depth"].as_u64().unwrap();
+    assert_eq!(depth, u64::from(MAX_AUTO_DEPTH));
+}
+
+#[smol_potat::test]
+async fn test_auto_depth_without_node_info() {
+    let _lock = lock_client();
+    let node = MockNode::start(|body| match body["command"].as_str().unwrap() {
+        "getNodeInfo" => (500, json!({ "error": "Node is down" }).to_string()),
+        _ => {
+            let tips = json!({ "trunkTransaction": TEST_TRUNK_HASH, "branchTransaction": TEST_BRANCH_HASH });
+            (200, tips.to_string())
+        }
+    });
+
+    assert!(Client::get_transactions_to_approve()
+        .depth(Depth::Auto)
+        .send()
+        .await
+        .is_err());
+    assert_eq!(node.commands(), vec!["getNodeInfo"]);
+}
+
+#[smol_potat::test]
+async fn test_request_and_response_hooks() {
+    let _lock = lock_client();
+    let _node = MockNode::start(|_| (200, node_info(TEST_MILESTONE_0)));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let responses = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    Client::on_request(move |body| recorded.lock().unwrap().push(body.to_string()));
+    let recorded = responses.clone();
+    Client::on_response(move |body| recorded.lock().unwrap().push(body.to_string()));
+
+    let res = Client::get_node_info().await;
+    Client::clear_hooks();
+    let _ = Client::get_node_info().await;
+
+    assert_eq!(res.unwrap().latest_milestone, TEST_MILESTONE_0);
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    let request: serde_json::Value = serde_json::from_str(&requests[0]).unwrap();
+    assert_eq!(request["command"], "getNodeInfo");
+    let responses = responses.lock().unwrap();
+    assert_eq!(responses.len(), 1);
+    let response: serde_json::Value = serde_json::from_str(&responses[0]).unwrap();
+    assert_eq!(response["appName"], "IRI");
+}
@@ -75,5 +75,5 @@ async fn test_were_addresses_spent_from() {
     .await
     .unwrap();
 
-    assert_eq!(res.states[0], false);
+    assert!(!res.states[0]);
 }
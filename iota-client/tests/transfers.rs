@@ -0,0 +1,250 @@
+//! Bundle and transaction tests which don't touch the network.
+mod common;
+use crate::common::*;
+use iota_bundle_preview::{Address, Hash, Transaction, TransactionField, Value};
+use iota_client::{
+    add_checksum, transaction_hash, validate_transaction, value_sum, Client, Error, Input, Transfer,
+};
+use iota_constants::IOTA_SUPPLY;
+use iota_conversion::Trinary;
+use iota_ternary_preview::TryteBuf;
+
+#[smol_potat::test]
+async fn test_prepare_transfers_remainder() {
+    let seed = seed();
+    let bundle = Client::prepare_transfers(&seed)
+        .transfers(vec![Transfer {
+            address: address(TEST_ADDRESS_0),
+            value: 3,
+            message: None,
+            tag: None,
+        }])
+        .security(1)
+        .inputs(vec![Input::new(address(TEST_TRUNK_HASH), 10, 0)])
+        .remainder(address(TEST_BRANCH_HASH))
+        .build()
+        .await
+        .unwrap();
+
+    let values: Vec<(String, i64)> = bundle
+        .into_iter()
+        .map(|tx| {
+            (
+                tx.address().to_inner().as_i8_slice().trytes().unwrap(),
+                *tx.value().to_inner(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            (TEST_ADDRESS_0.to_string(), 3),
+            (TEST_TRUNK_HASH.to_string(), -10),
+            (TEST_BRANCH_HASH.to_string(), 7),
+        ]
+    );
+}
+
+#[test]
+fn test_transaction_count() {
+    let seed = seed();
+    let transfer = |value, message: Option<&str>| Transfer {
+        address: address(TEST_ADDRESS_0),
+        value,
+        message: message.map(|m| m.to_string()),
+        tag: None,
+    };
+
+    let data = Client::prepare_transfers(&seed).transfers(vec![transfer(0, Some("IOTA"))]);
+    assert_eq!(data.transaction_count(), Some(1));
+
+    let long_message = "A".repeat(2000);
+    let data = Client::prepare_transfers(&seed).transfers(vec![transfer(0, Some(&long_message))]);
+    assert_eq!(data.transaction_count(), Some(2));
+
+    let value = Client::prepare_transfers(&seed)
+        .transfers(vec![transfer(10, None)])
+        .inputs(vec![Input::new(address(TEST_TRUNK_HASH), 10, 0)]);
+    assert_eq!(value.transaction_count(), Some(3));
+
+    let remainder = Client::prepare_transfers(&seed)
+        .transfers(vec![transfer(3, None)])
+        .inputs(vec![Input::new(address(TEST_TRUNK_HASH), 10, 0)]);
+    assert_eq!(remainder.transaction_count(), Some(4));
+
+    let unknown = Client::prepare_transfers(&seed).transfers(vec![transfer(3, None)]);
+    assert_eq!(unknown.transaction_count(), None);
+}
+
+#[smol_potat::test]
+async fn test_transaction_count_matches_bundle() {
+    let seed = seed();
+    let builder = Client::prepare_transfers(&seed)
+        .transfers(vec![Transfer {
+            address: address(TEST_ADDRESS_0),
+            value: 3,
+            message: Some("A".repeat(1500)),
+            tag: None,
+        }])
+        .inputs(vec![Input::new(address(TEST_TRUNK_HASH), 10, 0)])
+        .remainder(address(TEST_BRANCH_HASH));
+    let count = builder.transaction_count().unwrap();
+    assert_eq!(builder.build().await.unwrap().len(), count);
+}
+
+#[test]
+fn test_transaction_hash() {
+    let tx = Transaction::from_trits(TryteBuf::try_from_str(TEST_TX_TRYTES).unwrap().as_trits())
+        .unwrap();
+    assert_eq!(
+        transaction_hash(&tx).as_bytes().trytes().unwrap(),
+        TEST_TX_HASH
+    );
+}
+
+#[smol_potat::test]
+async fn test_transfer_values() {
+    let seed = seed();
+    let transfer = |value| Transfer {
+        address: address(TEST_ADDRESS_0),
+        value,
+        message: None,
+        tag: None,
+    };
+
+    let data = Client::prepare_transfers(&seed)
+        .transfers(vec![transfer(0)])
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(data.len(), 1);
+
+    Client::prepare_transfers(&seed)
+        .transfers(vec![transfer(10)])
+        .security(1)
+        .inputs(vec![Input::new(address(TEST_TRUNK_HASH), 10, 0)])
+        .build()
+        .await
+        .unwrap();
+
+    for transfers in [
+        vec![transfer(2_779_530_283_277_762)],
+        vec![transfer(2_779_530_283_277_761), transfer(1)],
+        vec![transfer(u64::MAX), transfer(u64::MAX)],
+    ] {
+        let err = match Client::prepare_transfers(&seed)
+            .transfers(transfers)
+            .build()
+            .await
+        {
+            Ok(_) => panic!("transfer values above the supply were accepted"),
+            Err(err) => err,
+        };
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::InvalidTransferValue(_))
+        ));
+    }
+}
+
+#[test]
+fn test_add_checksum() {
+    let bare = "LXQHWNY9CQOHPNMKFJFIJHGEPAENAOVFRDIBF99PPHDTWJDCGHLYETXT9NPUVSNKT9XDTDYNJKJCPQMZC";
+    assert_eq!(add_checksum(&address(bare)), bare.to_string() + "COZVXMTXC");
+}
+
+#[test]
+fn test_validate_transaction() {
+    let tx = Transaction::from_trits(TryteBuf::try_from_str(TEST_TX_TRYTES).unwrap().as_trits())
+        .unwrap();
+    validate_transaction(&tx, 14).unwrap();
+    // The hash ends with 14 zero trits
+    assert!(validate_transaction(&tx, 15).is_err());
+
+    let (_, trytes) = transaction(5, 1, Hash::zeros());
+    let tx = Transaction::from_trits(TryteBuf::try_from_str(&trytes).unwrap().as_trits()).unwrap();
+    assert!(validate_transaction(&tx, 0).is_err());
+}
+
+#[test]
+fn test_value_sum_overflow() {
+    let transaction = |value| {
+        transaction_builder(Address::zeros(), Hash::zeros(), 0, 0, Hash::zeros(), 0)
+            .with_value(Value::from_inner_unchecked(value))
+            .build()
+            .unwrap()
+    };
+    let supply = IOTA_SUPPLY as i64;
+    // Values of the supply which add up to exactly 2^64 wrap around to zero with unchecked addition
+    let count = (u64::MAX / IOTA_SUPPLY + 1) as i64;
+    let excess = (count as i128 * supply as i128 - (1i128 << 64)) as i64;
+    let mut bundle: Vec<Transaction> = (0..count).map(|_| transaction(supply)).collect();
+    bundle.push(transaction(-excess));
+    assert_eq!(
+        bundle
+            .iter()
+            .fold(0i64, |sum, tx| sum.wrapping_add(*tx.value().to_inner())),
+        0
+    );
+
+    let err = value_sum(&bundle).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::ValueOverflow)
+    ));
+    assert_eq!(value_sum(&bundle[..2]).unwrap(), 2 * supply);
+    assert_eq!(value_sum(&[transaction(5), transaction(-5)]).unwrap(), 0);
+}
+
+#[test]
+fn test_transfer_builder() {
+    let transfer = Transfer::builder()
+        .address(TEST_ADDRESS_0)
+        .value(10)
+        .message(&"A".repeat(1500))
+        .tag("client")
+        .build()
+        .unwrap();
+    assert_eq!(transfer.address, address(TEST_ADDRESS_0));
+    assert_eq!(transfer.value, 10);
+    // 1500 characters are encoded as 3000 trytes, which need two payload fragments
+    assert_eq!(transfer.transaction_count(), 2);
+    assert_eq!(
+        transfer
+            .tag
+            .unwrap()
+            .to_inner()
+            .as_i8_slice()
+            .trytes()
+            .unwrap(),
+        "CLIENT999999999999999999999"
+    );
+
+    let data = Transfer::builder().address(TEST_ADDRESS_0).build().unwrap();
+    assert_eq!(data.transaction_count(), 1);
+
+    assert!(Transfer::builder().build().is_err());
+    assert!(Transfer::builder()
+        .address(&TEST_ADDRESS_0[..80])
+        .build()
+        .is_err());
+    assert!(Transfer::builder()
+        .address(&TEST_ADDRESS_0.replace('A', "a"))
+        .build()
+        .is_err());
+    assert!(Transfer::builder()
+        .address(TEST_ADDRESS_0)
+        .value(IOTA_SUPPLY + 1)
+        .build()
+        .is_err());
+    assert!(Transfer::builder()
+        .address(TEST_ADDRESS_0)
+        .tag(&"A".repeat(28))
+        .build()
+        .is_err());
+    assert!(Transfer::builder()
+        .address(TEST_ADDRESS_0)
+        .message("ä")
+        .build()
+        .is_err());
+}
@@ -1,46 +1,122 @@
-use iota_bundle_preview::{Transaction, TransactionField};
-use iota_conversion::{trytes, Trinary};
+use anyhow::Result;
+use iota_bundle_preview::{Address, Hash, Transaction, TransactionField};
+use iota_constants::IOTA_SUPPLY;
+use iota_conversion::{trits_to_trytes_into, trytes, Trinary};
+use iota_crypto_preview::{CurlP81, Kerl, Sponge};
+use iota_ternary_preview::{T1B1Buf, TritBuf};
+
+use crate::error::Error;
+
+/// Size of a transaction in trits
+const TRANSACTION_TRIT_LEN: usize = 8019;
+
+/// Computes the hash of a transaction, which is the CurlP81 digest of its trits
+pub fn transaction_hash(tx: &Transaction) -> Hash {
+    let mut trits = TritBuf::<T1B1Buf>::zeros(TRANSACTION_TRIT_LEN);
+    tx.into_trits_allocated(&mut trits);
+    let digest = CurlP81::new().digest(&trits).unwrap();
+    Hash::from_inner_unchecked(digest)
+}
+
+/// Appends the checksum to an address, which is the last 9 trytes of its Kerl digest, so it can be
+/// displayed with protection against typos.
+pub fn add_checksum(address: &Address) -> String {
+    let digest = Kerl::default().digest(address.to_inner()).unwrap();
+    let checksum = trytes(&digest.as_i8_slice()[243 - 27..]).unwrap();
+    trytes(address.to_inner().as_i8_slice()).unwrap() + &checksum
+}
+
+/// Checks a single transaction before it is assembled into a bundle: its trytes length, that its
+/// hash has at least `mwm` trailing zero trits, that its index doesn't exceed its last index, and
+/// that its value is within the total supply.
+pub fn validate_transaction(tx: &Transaction, mwm: usize) -> Result<()> {
+    let len = tx_trytes(tx).len();
+    if len != TRANSACTION_TRIT_LEN / 3 {
+        return Err(anyhow!("Transaction has {} trytes", len));
+    }
+
+    let hash = transaction_hash(tx);
+    let zeros = hash
+        .as_bytes()
+        .iter()
+        .rev()
+        .take_while(|trit| **trit == 0)
+        .count();
+    if zeros < mwm {
+        return Err(anyhow!(
+            "Transaction hash has {} trailing zeros, minimum weight magnitude is {}",
+            zeros,
+            mwm
+        ));
+    }
+
+    if tx.index().to_inner() > tx.last_index().to_inner() {
+        return Err(anyhow!(
+            "Transaction index {} is greater than the last index {}",
+            tx.index().to_inner(),
+            tx.last_index().to_inner()
+        ));
+    }
+
+    let value = tx.value().to_inner().unsigned_abs();
+    if value > IOTA_SUPPLY {
+        return Err(Error::InvalidTransferValue(value).into());
+    }
+
+    Ok(())
+}
+
+/// Sums the values of the transactions of a bundle, failing with [`Error::ValueOverflow`] instead of
+/// wrapping around, so a crafted bundle can't pass a zero sum check.
+///
+/// [`Error::ValueOverflow`]: error/enum.Error.html#variant.ValueOverflow
+pub fn value_sum(bundle: &[Transaction]) -> Result<i64> {
+    bundle.iter().try_fold(0i64, |sum, tx| {
+        sum.checked_add(*tx.value().to_inner())
+            .ok_or_else(|| Error::ValueOverflow.into())
+    })
+}
+
+/// Checks a bundle fetched from nodes, ordered by index: all transactions belong to the same bundle
+/// and their values sum up to zero.
+pub(crate) fn validate_bundle(bundle: &[Transaction]) -> Result<()> {
+    // TODO validate the bundle hash and signatures once it's in iota_bundle_preview's bundle types
+    if let Some(tail) = bundle.first() {
+        if bundle.iter().any(|tx| tx.bundle() != tail.bundle()) {
+            return Err(anyhow!(
+                "Transactions of the bundle have different bundle hashes"
+            ));
+        }
+    }
+    if value_sum(bundle)? != 0 {
+        return Err(anyhow!("Bundle values don't sum up to zero"));
+    }
+    Ok(())
+}
 
 // TODO use bee-ternary once it porvides a method.
 /// Temporary util function to make a transaction trytes
 pub(crate) fn tx_trytes(tx: &Transaction) -> String {
-    let bundle = trytes(tx.bundle().as_bytes()).unwrap();
-    trytes(tx.payload().to_inner().as_i8_slice()).unwrap()
-        + &trytes(tx.address().to_inner().as_i8_slice()).unwrap()
-        + &tx
-            .value()
-            .to_inner()
-            .trits_with_length(81)
-            .trytes()
-            .unwrap()
-        + &trytes(tx.obsolete_tag().to_inner().as_i8_slice()).unwrap()
-        + &(*tx.timestamp().to_inner() as i64)
-            .trits_with_length(27)
-            .trytes()
-            .unwrap()
-        + &(*tx.index().to_inner() as i64)
-            .trits_with_length(27)
-            .trytes()
-            .unwrap()
-        + &(*tx.last_index().to_inner() as i64)
-            .trits_with_length(27)
-            .trytes()
-            .unwrap()
-        + &bundle
-        + &trytes(tx.trunk().as_bytes()).unwrap()
-        + &trytes(tx.branch().as_bytes()).unwrap()
-        + &trytes(tx.tag().to_inner().as_i8_slice()).unwrap()
-        + &(*tx.attachment_ts().to_inner() as i64)
-            .trits_with_length(27)
-            .trytes()
-            .unwrap()
-        + &(*tx.attachment_lbts().to_inner() as i64)
-            .trits_with_length(27)
-            .trytes()
-            .unwrap()
-        + &(*tx.attachment_ubts().to_inner() as i64)
-            .trits_with_length(27)
-            .trytes()
-            .unwrap()
-        + &trytes(tx.nonce().to_inner().as_i8_slice()).unwrap()
+    let number = |value: i64, len| value.trits_with_length(len);
+    let mut trytes = String::with_capacity(TRANSACTION_TRIT_LEN / 3);
+    for trits in &[
+        tx.payload().to_inner().as_i8_slice(),
+        tx.address().to_inner().as_i8_slice(),
+        &number(*tx.value().to_inner(), 81),
+        tx.obsolete_tag().to_inner().as_i8_slice(),
+        &number(*tx.timestamp().to_inner() as i64, 27),
+        &number(*tx.index().to_inner() as i64, 27),
+        &number(*tx.last_index().to_inner() as i64, 27),
+        tx.bundle().as_bytes(),
+        tx.trunk().as_bytes(),
+        tx.branch().as_bytes(),
+        tx.tag().to_inner().as_i8_slice(),
+        &number(*tx.attachment_ts().to_inner() as i64, 27),
+        &number(*tx.attachment_lbts().to_inner() as i64, 27),
+        &number(*tx.attachment_ubts().to_inner() as i64, 27),
+        tx.nonce().to_inner().as_i8_slice(),
+    ] {
+        trits_to_trytes_into(trits, &mut trytes).unwrap();
+    }
+    trytes
 }
@@ -15,6 +15,9 @@ pub const TRYTE_ALPHABET: [char; 27] = [
     'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
 ];
 
+/// Total supply of IOTA tokens
+pub const IOTA_SUPPLY: u64 = 2_779_530_283_277_761;
+
 /// Default Minimum Weight Magnitude on IOTA mainnet
 pub const DEFAULT_MWM: usize = 14;
 
@@ -51,8 +54,11 @@ pub const ADDRESS_WITH_CHECKSUM_TRYTES_SIZE: usize =
 /// Size of minimum checksum
 pub const MIN_CHECKSUM_TRYTES_SIZE: usize = 3;
 
-/// Maximum value of attachment timstamp
-pub const UPPER_BOUND_ATTACHMENT_TIMESTAMP: usize = (3 ^ 27 - 1) / 2;
+/// Maximum value of attachment timstamp, the largest number 27 balanced trits can hold
+///```
+/// assert_eq!(iota_constants::UPPER_BOUND_ATTACHMENT_TIMESTAMP, 3_812_798_742_493);
+///```
+pub const UPPER_BOUND_ATTACHMENT_TIMESTAMP: usize = (3_usize.pow(27) - 1) / 2;
 /// Minimum value of attachment timstamp
 pub const LOWER_BOUND_ATTACHMENT_TIMESTAMP: usize = 0;
 
@@ -0,0 +1,122 @@
+//! Compares the converters with the slower paths they replaced.
+//! Run with `cargo bench -p iota-conversion`.
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::time::Instant;
+
+use iota_constants::{TRITS_PER_TRYTE, TRYTE_ALPHABET};
+use iota_conversion::{
+    alphabet_char, alphabet_index, trytes, trytes_converter, trytes_to_trits, Trinary,
+    TRYTE_TO_TRITS_MAPPINGS,
+};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+/// Times both paths, checks they agree and prints how much faster `current` is
+fn compare<T: PartialEq + Debug>(
+    input: &str,
+    (baseline_name, baseline): (&str, impl FnOnce() -> T),
+    (name, current): (&str, impl FnOnce() -> T),
+) {
+    let start = Instant::now();
+    let expected = baseline();
+    let baseline_time = start.elapsed();
+    let start = Instant::now();
+    let actual = current();
+    let time = start.elapsed();
+
+    assert_eq!(actual, expected);
+    println!(
+        "{}: {} {:?}, {} {:?}, {:.1}x",
+        input,
+        baseline_name,
+        baseline_time,
+        name,
+        time,
+        baseline_time.as_secs_f64() / time.as_secs_f64()
+    );
+}
+
+fn random_trytes(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| alphabet_char(rng.gen_range(0, 27)).unwrap())
+        .collect()
+}
+
+fn collected_trits(trytes: &str) -> Vec<i8> {
+    trytes
+        .chars()
+        .flat_map(|tryte| TRYTE_TO_TRITS_MAPPINGS[alphabet_index(tryte).unwrap()].iter())
+        .cloned()
+        .collect()
+}
+
+fn main() {
+    // What Trinary::trits and trytes did before they preallocated
+    let s = random_trytes(100_000);
+    compare(
+        "100KB trits and trytes",
+        ("collected", || {
+            let trits = collected_trits(&s);
+            let trytes: String = trits
+                .chunks(TRITS_PER_TRYTE)
+                .map(|tryte| {
+                    let index = TRYTE_TO_TRITS_MAPPINGS.iter().position(|x| x == tryte);
+                    alphabet_char(index.unwrap()).unwrap()
+                })
+                .collect();
+            (trits, trytes)
+        }),
+        ("preallocated", || {
+            let trits = s.trits();
+            let trytes = trytes(&trits).unwrap();
+            (trits, trytes)
+        }),
+    );
+
+    let s = random_trytes(1000 * 2673);
+    compare(
+        "1000 transactions",
+        ("collected", || collected_trits(&s)),
+        ("trytes_to_trits", || trytes_to_trits(&s).unwrap()),
+    );
+
+    // The map lookups to_trytes used before
+    let map: HashMap<char, usize> = (0..=126u8).map(|b| (b as char, b as usize)).collect();
+    let s: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(1 << 20)
+        .collect();
+    compare(
+        "1MB string",
+        ("map", || {
+            let mut trytes = String::new();
+            for c in s.chars() {
+                let ascii = map[&c];
+                trytes.push(TRYTE_ALPHABET[ascii % 27]);
+                trytes.push(TRYTE_ALPHABET[ascii / 27]);
+            }
+            trytes
+        }),
+        ("to_trytes", || trytes_converter::to_trytes(&s).unwrap()),
+    );
+
+    // A byte at a time, which to_trytes_bytes falls back to without AVX2
+    let mut rng = rand::thread_rng();
+    let bytes: Vec<u8> = (0..1 << 20).map(|_| rng.gen()).collect();
+    compare(
+        "1MB bytes",
+        ("scalar", || {
+            let mut trytes = String::with_capacity(bytes.len() * 2);
+            for &b in &bytes {
+                trytes.push(TRYTE_ALPHABET[b as usize % 27]);
+                trytes.push(TRYTE_ALPHABET[b as usize / 27]);
+            }
+            trytes
+        }),
+        ("to_trytes_bytes", || {
+            trytes_converter::to_trytes_bytes(&bytes)
+        }),
+    );
+}
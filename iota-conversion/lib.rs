@@ -16,6 +16,8 @@ extern crate lazy_static;
 /// Provides useful unit definitions for Iota
 pub mod iota_units;
 mod trinary;
+/// Trit arithmetic used by Curl and the encoding of numeric fields
+pub mod trit_adder;
 /// Converts between strings and tryte-encoded strings
 pub mod trytes_converter;
 /// Provides converters between various unit representations of Iota
@@ -25,15 +27,18 @@ pub use trinary::*;
 
 type Result<T> = ::std::result::Result<T, failure::Error>;
 
-/// Converts a slice of trits into a numeric value
+/// Converts a slice of trits into a numeric value. Values of more than 5 trits wrap around.
 pub fn value(trits: &[i8]) -> i8 {
-    trits.iter().rev().fold(0, |acc, trit| acc * 3 + *trit)
-}
-
-/// Converts a slice of trits into a numeric value in i64
-pub fn long_value(trits: &[i8]) -> i64 {
     trits
         .iter()
         .rev()
-        .fold(0, |acc, trit| acc * 3 + i64::from(*trit))
+        .fold(0i8, |acc, trit| acc.wrapping_mul(3).wrapping_add(*trit))
+}
+
+/// Converts a slice of trits into a numeric value in i64. Values of more than 39 trits wrap
+/// around, use `trytes_to_value` to detect overflows.
+pub fn long_value(trits: &[i8]) -> i64 {
+    trits.iter().rev().fold(0i64, |acc, trit| {
+        acc.wrapping_mul(3).wrapping_add(i64::from(*trit))
+    })
 }
@@ -1,5 +1,9 @@
 use crate::Result;
-use iota_constants::{TRINARY_RADIX, TRITS_PER_BYTE, TRITS_PER_TRYTE};
+use iota_constants::{
+    HASH_BYTES_SIZE, HASH_TRINARY_SIZE, HASH_TRYTES_SIZE, TRINARY_RADIX, TRITS_PER_BYTE,
+    TRITS_PER_TRYTE,
+};
+use std::convert::TryInto;
 
 lazy_static! {
     /// Provides a byte to trits mapping
@@ -12,16 +16,6 @@ lazy_static! {
         });
         tmp
     };
-    /// Provides a trytes to trits mapping
-    pub static ref TRYTE_TO_TRITS_MAPPINGS: [[i8; TRITS_PER_TRYTE]; 27] = {
-        let mut trits: [i8; TRITS_PER_BYTE] = [0; TRITS_PER_BYTE];
-        let mut tmp = [[0; TRITS_PER_TRYTE]; 27];
-        tmp.iter_mut().for_each(|tmp_entry| {
-            tmp_entry.copy_from_slice(&trits[0..TRITS_PER_TRYTE]);
-            increment(&mut trits, TRITS_PER_TRYTE);
-        });
-        tmp
-    };
 }
 
 /// Trait used to enable conversion to trinary types
@@ -42,10 +36,11 @@ pub type Trytes = String;
 impl Trinary for i64 {
     fn trits(&self) -> Vec<Trit> {
         let mut trits = Vec::new();
-        let mut abs = self.abs();
+        // Unsigned, since the absolute value of i64::MIN doesn't fit into an i64
+        let mut abs = self.unsigned_abs();
         while abs > 0 {
-            let mut remainder = (abs % i64::from(TRINARY_RADIX as i8)) as i8;
-            abs /= i64::from(TRINARY_RADIX as i8);
+            let mut remainder = (abs % TRINARY_RADIX as u64) as i8;
+            abs /= TRINARY_RADIX as u64;
             if remainder > iota_constants::MAX_TRIT_VALUE {
                 remainder = iota_constants::MIN_TRIT_VALUE;
                 abs += 1;
@@ -99,17 +94,13 @@ impl Trinary for [Trit; 243] {
         trits_with_length(&self.trits(), length)
     }
     fn trytes(&self) -> Result<Trytes> {
-        ensure!(self.len() % 3 == 0, "Invalid trit length.");
-
-        self.chunks(iota_constants::TRITS_PER_TRYTE)
-            .map(trits_to_char)
-            .collect()
+        trytes(self)
     }
 }
 
 impl Trinary for Trytes {
     fn trits(&self) -> Vec<Trit> {
-        self.chars().flat_map(char_to_trits).cloned().collect()
+        str_to_trits(self)
     }
     fn trits_with_length(&self, length: usize) -> Vec<Trit> {
         trits_with_length(&self.trits(), length)
@@ -121,7 +112,7 @@ impl Trinary for Trytes {
 
 impl Trinary for &str {
     fn trits(&self) -> Vec<Trit> {
-        self.chars().flat_map(char_to_trits).cloned().collect()
+        str_to_trits(self)
     }
     fn trits_with_length(&self, length: usize) -> Vec<Trit> {
         trits_with_length(&self.trits(), length)
@@ -143,46 +134,676 @@ fn increment(trit_array: &mut [Trit], size: usize) {
     }
 }
 
+// Collecting the flattened chars would grow the vector several times for long strings
+fn str_to_trits(trytes: &str) -> Vec<Trit> {
+    let mut trits = Vec::with_capacity(trytes.len() * TRITS_PER_TRYTE);
+    for tryte in trytes.chars() {
+        trits.extend_from_slice(char_to_trits(tryte));
+    }
+    trits
+}
+
 fn char_to_trits(tryte: char) -> &'static [Trit] {
-    match iota_constants::TRYTE_ALPHABET
-        .iter()
-        .position(|&x| x == tryte)
-    {
+    match alphabet_index(tryte) {
         Some(p) => &TRYTE_TO_TRITS_MAPPINGS[p],
         None => &TRYTE_TO_TRITS_MAPPINGS[0],
     }
 }
 
+/// Position of a tryte in `TRYTE_ALPHABET`, without scanning the alphabet, or `None` if the
+/// character isn't a tryte
+pub const fn alphabet_index(tryte: char) -> Option<usize> {
+    match tryte {
+        '9' => Some(0),
+        'A'..='Z' => Some(tryte as usize - 'A' as usize + 1),
+        _ => None,
+    }
+}
+
+// `alphabet_index` and the `% 27` math of the converters rest on `TRYTE_ALPHABET` being `9`
+// followed by `A` to `Z`, so the build fails if the constant is ever reordered.
+const _: () = {
+    let mut index = 0;
+    while index < iota_constants::TRYTE_ALPHABET.len() {
+        match alphabet_index(iota_constants::TRYTE_ALPHABET[index]) {
+            Some(position) if position == index => {}
+            _ => panic!("TRYTE_ALPHABET is not 9 followed by A to Z"),
+        }
+        index += 1;
+    }
+};
+
+/// Tryte at the given position of `TRYTE_ALPHABET`, or `None` if the index is 27 or above
+pub fn alphabet_char(index: usize) -> Option<char> {
+    iota_constants::TRYTE_ALPHABET.get(index).copied()
+}
+
+/// Converts trytes to their positions in `TRYTE_ALPHABET`, failing on characters outside the
+/// tryte alphabet
+///```
+/// use iota_conversion::trytes_to_indices;
+///
+/// assert_eq!(trytes_to_indices("9AZ").unwrap(), vec![0, 1, 26]);
+///```
+pub fn trytes_to_indices(trytes: &str) -> Result<Vec<u8>> {
+    trytes
+        .chars()
+        .enumerate()
+        .map(|(position, tryte)| match alphabet_index(tryte) {
+            Some(index) => Ok(index as u8),
+            None => bail!("Invalid tryte at position {}", position),
+        })
+        .collect()
+}
+
+/// Converts positions in `TRYTE_ALPHABET` to trytes, failing on indices of 27 or above
+///```
+/// use iota_conversion::indices_to_trytes;
+///
+/// assert_eq!(indices_to_trytes(&[0, 1, 26]).unwrap(), "9AZ");
+///```
+pub fn indices_to_trytes(indices: &[u8]) -> Result<Trytes> {
+    let mut trytes = String::with_capacity(indices.len());
+    for (position, index) in indices.iter().enumerate() {
+        match alphabet_char(*index as usize) {
+            Some(tryte) => trytes.push(tryte),
+            None => bail!("Invalid tryte index {} at position {}", index, position),
+        }
+    }
+    Ok(trytes)
+}
+
+/// Provides a trytes to trits mapping, the trits of each tryte in `TRYTE_ALPHABET` order,
+/// computed at compile time
+pub const TRYTE_TO_TRITS_MAPPINGS: [[Trit; TRITS_PER_TRYTE]; 27] = tryte_trits_table();
+
+const fn tryte_trits_table() -> [[Trit; TRITS_PER_TRYTE]; 27] {
+    let mut table = [[0; TRITS_PER_TRYTE]; 27];
+    let mut index = 0;
+    while index < 27 {
+        // Trytes after `M` have negative values
+        let mut value = if index > 13 {
+            index as i8 - 27
+        } else {
+            index as i8
+        };
+        let mut i = 0;
+        while i < TRITS_PER_TRYTE {
+            let mut trit = value % 3;
+            if trit > 1 {
+                trit -= 3;
+            } else if trit < -1 {
+                trit += 3;
+            }
+            table[index][i] = trit;
+            value = (value - trit) / 3;
+            i += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+/// Converts trytes to trits in a single pass over their bytes, failing on characters outside the
+/// tryte alphabet. The output is the same as `Trinary::trits`, but this is the faster path for
+/// large inputs like whole transactions.
+pub fn trytes_to_trits(trytes: &str) -> Result<Vec<Trit>> {
+    let mut trits = Vec::new();
+    trytes_to_trits_into(trytes, &mut trits)?;
+    Ok(trits)
+}
+
+/// Appends the trits of the given trytes to `out` like `trytes_to_trits`, failing on characters
+/// outside the tryte alphabet. `out` is left unchanged on failure.
+pub fn trytes_to_trits_into(trytes: &str, out: &mut Vec<Trit>) -> Result<()> {
+    let len = out.len();
+    out.reserve(trytes.len() * TRITS_PER_TRYTE);
+    // Everything before the first invalid byte is ascii, so its byte position is its char position
+    for (position, tryte) in trytes.bytes().enumerate() {
+        let index = match tryte {
+            b'9' => 0,
+            b'A'..=b'Z' => (tryte - b'A' + 1) as usize,
+            _ => {
+                out.truncate(len);
+                bail!("Invalid tryte at position {}", position);
+            }
+        };
+        out.extend_from_slice(&TRYTE_TO_TRITS_MAPPINGS[index]);
+    }
+    Ok(())
+}
+
 fn trits_to_char(trits: &[Trit]) -> Result<char> {
     ensure!(
         trits.len() <= iota_constants::TRITS_PER_TRYTE,
         "Provided trit slice is too long: {:?}",
         trits
     );
-    Ok(
-        match TRYTE_TO_TRITS_MAPPINGS.iter().position(|&x| x == trits) {
-            Some(p) => iota_constants::TRYTE_ALPHABET[p],
-            None => '-',
-        },
-    )
+    Ok(TRYTE_TO_TRITS_MAPPINGS
+        .iter()
+        .position(|&x| x == trits)
+        .and_then(alphabet_char)
+        .unwrap_or('-'))
 }
 
 /// Temporary usage to convert trits to tryte strings
 pub fn trytes(trits: &[Trit]) -> Result<Trytes> {
     ensure!(trits.len() % 3 == 0, "Invalid trit length.");
 
+    let mut trytes = String::new();
+    trits_to_trytes_into(trits, &mut trytes)?;
+    Ok(trytes)
+}
+
+/// Appends the trytes of the given trits to `out`, so one buffer can be reused for several fields.
+/// `out` is left unchanged on failure.
+///```
+/// use iota_conversion::trits_to_trytes_into;
+///
+/// let mut trytes = String::from("IOTA");
+/// trits_to_trytes_into(&[1, 0, 0, -1, 1, 0], &mut trytes).unwrap();
+/// assert_eq!(trytes, "IOTAAB");
+///```
+pub fn trits_to_trytes_into(trits: &[Trit], out: &mut String) -> Result<()> {
+    ensure!(trits.len() % 3 == 0, "Invalid trit length.");
+
+    // Collecting into a Result wouldn't pass on the length, so the string is reserved upfront
+    out.reserve(trits.len() / iota_constants::TRITS_PER_TRYTE);
+    for chunk in trits.chunks(iota_constants::TRITS_PER_TRYTE) {
+        out.push(trits_to_char(chunk)?);
+    }
+    Ok(())
+}
+
+/// Compares two addresses by their 81 trytes body, so either of them may carry a checksum
+pub fn trytes_equal_ignoring_checksum(a: &str, b: &str) -> Result<bool> {
+    Ok(strip_checksum(a)? == strip_checksum(b)?)
+}
+
+/// Uppercases a tag and pads it with `9` to 27 trytes
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let mut tag = tag.to_ascii_uppercase();
+    ensure!(
+        tag.chars()
+            .all(|c| iota_constants::TRYTE_ALPHABET.contains(&c)),
+        "Invalid tag trytes: {}",
+        tag
+    );
+    ensure!(
+        tag.len() <= iota_constants::TAG_LENGTH,
+        "Tag is longer than {} trytes: {}",
+        iota_constants::TAG_LENGTH,
+        tag
+    );
+
+    while tag.len() < iota_constants::TAG_LENGTH {
+        tag.push('9');
+    }
+    Ok(tag)
+}
+
+/// Removes the `9` padding at the end of trytes. Most fields pad on this side: messages and tags
+/// are left-aligned, and numeric fields like `value`, `timestamp` and indexes start with their
+/// least significant tryte.
+///```
+/// assert_eq!(iota_conversion::unpad_trytes("IOTA9999"), "IOTA");
+///```
+pub fn unpad_trytes(trytes: &str) -> &str {
+    trytes.trim_end_matches('9')
+}
+
+/// Removes the `9` padding at the start of trytes, for right-aligned values. Combine it with
+/// [`unpad_trytes`] to trim both sides.
+///
+/// [`unpad_trytes`]: fn.unpad_trytes.html
+///```
+/// use iota_conversion::{trim_left_nines, unpad_trytes};
+///
+/// assert_eq!(trim_left_nines("99IOTA"), "IOTA");
+/// assert_eq!(unpad_trytes(trim_left_nines("99IOTA99")), "IOTA");
+///```
+pub fn trim_left_nines(trytes: &str) -> &str {
+    trytes.trim_start_matches('9')
+}
+
+/// Decodes trytes, like the 27 trytes of a transaction's `value` field, as a balanced ternary
+/// number, least significant tryte first. This is the tryte-level counterpart of `long_value`,
+/// but it fails instead of overflowing if the value doesn't fit into an i64.
+pub fn trytes_to_value(trytes: &str) -> Result<i64> {
+    let trits = trytes_to_trits(trytes)?;
+    // Partial sums may leave the i64 range even if the value doesn't, e.g. for i64::MIN, so they
+    // are computed in i128.
     trits
-        .chunks(iota_constants::TRITS_PER_TRYTE)
-        .map(trits_to_char)
+        .iter()
+        .rev()
+        .try_fold(0i128, |acc, trit| {
+            acc.checked_mul(TRINARY_RADIX as i128)?
+                .checked_add(i128::from(*trit))
+        })
+        .and_then(|value| value.try_into().ok())
+        .ok_or_else(|| format_err!("Value of trytes {} doesn't fit into an i64", trytes))
+}
+
+/// Encodes a value as `tryte_len` trytes of balanced ternary, padded with `9`. Trits which don't
+/// fit into `tryte_len` trytes are cut off, so the width has to be chosen for the value range.
+pub fn value_to_trytes(value: i64, tryte_len: usize) -> String {
+    // The trits of a whole number of trytes always map to valid trytes.
+    value
+        .trits_with_length(tryte_len * TRITS_PER_TRYTE)
+        .trytes()
+        .unwrap()
+}
+
+/// Number of 32 bit limbs of the 384 bit integer behind the byte encoding of a hash
+const HASH_LIMBS: usize = HASH_BYTES_SIZE / 4;
+
+/// Converts 81 trytes into the 48 bytes Kerl feeds to Keccak-384: the trits, with the 243rd
+/// trit zeroed, are read as a balanced ternary number and written as a big endian 384 bit
+/// two's complement integer.
+pub fn trytes_to_bytes_48(trytes: &str) -> Result<[u8; HASH_BYTES_SIZE]> {
+    ensure!(
+        trytes.len() == HASH_TRYTES_SIZE,
+        "Invalid hash length: {}",
+        trytes.len()
+    );
+    let mut trits = Vec::with_capacity(HASH_TRINARY_SIZE);
+    trytes_to_trits_into(trytes, &mut trits)?;
+
+    // Little endian limbs, computed modulo 2^384 so negative values end up in two's complement.
+    let mut limbs = [0u32; HASH_LIMBS];
+    for trit in trits[..HASH_TRINARY_SIZE - 1].iter().rev() {
+        let mut carry = i64::from(*trit);
+        for limb in limbs.iter_mut() {
+            let v = i64::from(*limb) * TRINARY_RADIX as i64 + carry;
+            *limb = v as u32;
+            carry = v >> 32;
+        }
+    }
+
+    let mut bytes = [0; HASH_BYTES_SIZE];
+    for (chunk, limb) in bytes.chunks_mut(4).zip(limbs.iter().rev()) {
+        chunk.copy_from_slice(&limb.to_be_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Converts the 48 bytes of a big endian 384 bit two's complement integer into 81 trytes, the
+/// inverse of [`trytes_to_bytes_48`]. The 243rd trit is always zero, so values which don't fit
+/// into 242 trits are truncated.
+///
+/// [`trytes_to_bytes_48`]: fn.trytes_to_bytes_48.html
+pub fn bytes_48_to_trytes(bytes: &[u8; HASH_BYTES_SIZE]) -> String {
+    let mut limbs = [0u32; HASH_LIMBS];
+    for (limb, chunk) in limbs.iter_mut().rev().zip(bytes.chunks(4)) {
+        *limb = u32::from_be_bytes(chunk.try_into().unwrap());
+    }
+
+    // Convert the absolute value and flip the trits of negative values afterwards.
+    let negative = limbs[HASH_LIMBS - 1] >> 31 == 1;
+    if negative {
+        limbs.iter_mut().for_each(|limb| *limb = !*limb);
+        increment_limbs(&mut limbs);
+    }
+
+    let mut trits = [0; HASH_TRINARY_SIZE];
+    for trit in trits[..HASH_TRINARY_SIZE - 1].iter_mut() {
+        let mut remainder = 0u64;
+        for limb in limbs.iter_mut().rev() {
+            let v = (remainder << 32) | u64::from(*limb);
+            *limb = (v / TRINARY_RADIX as u64) as u32;
+            remainder = v % TRINARY_RADIX as u64;
+        }
+        *trit = match remainder {
+            0 => 0,
+            1 => 1,
+            _ => {
+                increment_limbs(&mut limbs);
+                -1
+            }
+        };
+        if negative {
+            *trit = -*trit;
+        }
+    }
+
+    trits
+        .chunks(TRITS_PER_TRYTE)
+        .map(|tryte| {
+            let value = crate::value(tryte) + iota_constants::TRYTE_ALPHABET.len() as i8;
+            // The value of balanced trits is at least -13, so the index is always in range.
+            alphabet_char(value as usize % iota_constants::TRYTE_ALPHABET.len()).unwrap()
+        })
         .collect()
 }
 
+fn increment_limbs(limbs: &mut [u32]) {
+    for limb in limbs.iter_mut() {
+        let (sum, overflow) = limb.overflowing_add(1);
+        *limb = sum;
+        if !overflow {
+            break;
+        }
+    }
+}
+
+fn strip_checksum(trytes: &str) -> Result<&str> {
+    ensure!(
+        trytes.len() == iota_constants::ADDRESS_LENGTH_WITHOUT_CHECKSUM
+            || trytes.len() == iota_constants::ADDRESS_LENGTH_WITH_CHECKSUM,
+        "Invalid address length: {}",
+        trytes.len()
+    );
+
+    trytes
+        .get(..iota_constants::ADDRESS_LENGTH_WITHOUT_CHECKSUM)
+        .ok_or_else(|| format_err!("Invalid address trytes: {}", trytes))
+}
+
 fn trits_with_length(trits: &[Trit], length: usize) -> Vec<Trit> {
     if trits.len() < length {
         let mut result = vec![0; length];
-        result[..trits.len()].copy_from_slice(&trits);
+        result[..trits.len()].copy_from_slice(trits);
         result
     } else {
         trits[..length].to_vec()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{self, Rng};
+
+    use super::*;
+
+    const ADDRESS: &str =
+        "XUERGHWTYRTFUYKFKXURKHMFEVLOIFTTCNTXOGLDPCZ9CJLKHROOPGNAQYFJEPGK9OKUQROUECBAVNXRY";
+    const ADDRESS_WITH_CHECKSUM: &str = "XUERGHWTYRTFUYKFKXURKHMFEVLOIFTTCNTXOGLDPCZ9CJLKHROOPGNAQYFJEPGK9OKUQROUECBAVNXRYABCDEFGHI";
+    const OTHER_ADDRESS: &str =
+        "RVORZ9SIIP9RCYMREUIXXVPQIPHVCNPQ9HZWYKFWYWZRE9JQKG9REPKIASHUUECPSQO9JT9XNMVKWYGVA";
+    const HASH: &str =
+        "GNSMASOK9OLEQJTFVPNCQDHXHOOJJB9OLEFZM9GDWMNHKYFELSOZDRCXROZGSUPS9OUXNABVWZACA9999";
+    // Byte form of HASH as computed by the bigint conversion of Kerl
+    const HASH_BYTES: [u8; 48] = [
+        0, 0, 2, 224, 208, 115, 71, 44, 93, 254, 172, 105, 235, 117, 13, 4, 221, 146, 221, 28, 6,
+        18, 175, 232, 202, 14, 142, 185, 195, 10, 3, 151, 59, 193, 157, 168, 18, 209, 254, 36, 170,
+        4, 73, 73, 80, 172, 198, 6,
+    ];
+
+    #[test]
+    fn should_compare_trytes_ignoring_checksum() {
+        assert!(trytes_equal_ignoring_checksum(ADDRESS_WITH_CHECKSUM, ADDRESS).unwrap());
+        assert!(trytes_equal_ignoring_checksum(ADDRESS, ADDRESS).unwrap());
+        assert!(!trytes_equal_ignoring_checksum(ADDRESS_WITH_CHECKSUM, OTHER_ADDRESS).unwrap());
+    }
+
+    #[test]
+    fn should_normalize_tag() {
+        assert_eq!(
+            normalize_tag("iota").unwrap(),
+            "IOTA99999999999999999999999"
+        );
+        assert_eq!(
+            normalize_tag("CCLIENT99999999999999999999").unwrap(),
+            "CCLIENT99999999999999999999"
+        );
+        assert!(normalize_tag("CCLIENT999999999999999999999").is_err());
+        assert!(normalize_tag("IOTA-TAG").is_err());
+    }
+
+    #[test]
+    fn should_append_trits() {
+        let mut trits = vec![1, -1];
+        trytes_to_trits_into("9AZ", &mut trits).unwrap();
+        assert_eq!(trits, vec![1, -1, 0, 0, 0, 1, 0, 0, -1, 0, 0]);
+        assert_eq!(&trits[2..], &"9AZ".trits()[..]);
+
+        assert!(trytes_to_trits_into("AB-", &mut trits).is_err());
+        assert_eq!(trits.len(), 11);
+    }
+
+    #[test]
+    fn should_convert_trytes_to_bytes_48_and_back() {
+        for trytes in &[
+            ADDRESS,
+            OTHER_ADDRESS,
+            HASH,
+            &"9".repeat(81),
+            &format!("{}W", "N".repeat(80)),
+        ] {
+            let bytes = trytes_to_bytes_48(trytes).unwrap();
+            assert_eq!(&bytes_48_to_trytes(&bytes), trytes);
+        }
+    }
+
+    #[test]
+    fn should_convert_trytes_to_bytes_48_like_kerl() {
+        assert_eq!(trytes_to_bytes_48(HASH).unwrap(), HASH_BYTES);
+        assert_eq!(bytes_48_to_trytes(&HASH_BYTES), HASH);
+        assert_eq!(trytes_to_bytes_48(&"9".repeat(81)).unwrap(), [0; 48]);
+        // The 243rd trit is ignored
+        let last = format!("{}M", &HASH[..80]);
+        let without_last = format!("{}D", &HASH[..80]);
+        assert_eq!(
+            trytes_to_bytes_48(&last).unwrap(),
+            trytes_to_bytes_48(&without_last).unwrap()
+        );
+        assert!(trytes_to_bytes_48(&HASH[..80]).is_err());
+        assert!(trytes_to_bytes_48(&format!("{}-", &HASH[..80])).is_err());
+    }
+
+    #[test]
+    fn should_convert_trytes_to_trits_with_table() {
+        for (index, tryte) in iota_constants::TRYTE_ALPHABET.iter().enumerate() {
+            let trits = trytes_to_trits(&tryte.to_string()).unwrap();
+            let value = if index > 13 {
+                index as i8 - 27
+            } else {
+                index as i8
+            };
+            assert_eq!(crate::value(&trits), value);
+            assert!(trits.iter().all(|trit| (-1..=1).contains(trit)));
+            assert_eq!(trits, tryte.to_string().trits());
+        }
+
+        let trytes = format!("{}{}", ADDRESS_WITH_CHECKSUM, HASH).repeat(15);
+        assert_eq!(trytes_to_trits(&trytes).unwrap(), trytes.trits());
+        assert!(trytes_to_trits("ABc").is_err());
+        assert_eq!(
+            trytes_to_trits("ABc").unwrap_err().to_string(),
+            "Invalid tryte at position 2"
+        );
+        assert_eq!(
+            trytes_to_trits_into("AB\u{e9}", &mut Vec::new())
+                .unwrap_err()
+                .to_string(),
+            "Invalid tryte at position 2"
+        );
+        assert!(trytes_to_trits("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_access_alphabet() {
+        assert_eq!(alphabet_char(0), Some('9'));
+        assert_eq!(alphabet_char(26), Some('Z'));
+        assert_eq!(alphabet_char(27), None);
+        assert_eq!(alphabet_index('a'), None);
+        assert_eq!(alphabet_index('-'), None);
+        for (index, tryte) in iota_constants::TRYTE_ALPHABET.iter().enumerate() {
+            assert_eq!(alphabet_index(*tryte), Some(index));
+            assert_eq!(alphabet_char(index), Some(*tryte));
+        }
+    }
+
+    #[test]
+    fn should_convert_indices() {
+        let indices = trytes_to_indices(HASH).unwrap();
+        assert_eq!(indices.len(), 81);
+        assert!(indices.iter().all(|index| *index < 27));
+        assert_eq!(indices_to_trytes(&indices).unwrap(), HASH);
+        assert!(indices_to_trytes(&[]).unwrap().is_empty());
+
+        assert!(trytes_to_indices("AB-").is_err());
+        assert!(indices_to_trytes(&[0, 26, 27]).is_err());
+    }
+
+    #[test]
+    fn should_trim_nines() {
+        assert_eq!(trim_left_nines("999A9B"), "A9B");
+        assert_eq!(unpad_trytes("999A9B"), "999A9B");
+        assert_eq!(unpad_trytes("A9B999"), "A9B");
+        assert_eq!(trim_left_nines("A9B999"), "A9B999");
+        assert_eq!(unpad_trytes(trim_left_nines("99A9B99")), "A9B");
+        assert_eq!(trim_left_nines(&"9".repeat(27)), "");
+        assert_eq!(unpad_trytes(&"9".repeat(27)), "");
+    }
+
+    #[test]
+    fn should_convert_trytes_to_value_and_back() {
+        assert_eq!(trytes_to_value(&"9".repeat(27)).unwrap(), 0);
+        assert_eq!(value_to_trytes(0, 27), "9".repeat(27));
+        assert_eq!(trytes_to_value("A9").unwrap(), 1);
+        assert_eq!(trytes_to_value("Z9").unwrap(), -1);
+        assert_eq!(trytes_to_value("ZA").unwrap(), 26);
+        assert_eq!(value_to_trytes(-27, 3), "9Z9");
+
+        let supply = iota_constants::IOTA_SUPPLY as i64;
+        for value in [1, -1, 42, -1000, 2_779_530_283, supply, -supply] {
+            let trytes = value_to_trytes(value, 27);
+            assert_eq!(trytes.len(), 27);
+            assert_eq!(trytes_to_value(&trytes).unwrap(), value);
+        }
+
+        assert!(trytes_to_value(&"M".repeat(27)).is_err());
+        assert!(trytes_to_value(&"M".repeat(81)).is_err());
+        assert!(trytes_to_value("a").is_err());
+    }
+
+    /// Random trytes of up to 300 chars, including some chars outside of the alphabet
+    fn random_strings() -> Vec<String> {
+        let pool: Vec<char> = "9ABMNZaz -\0äé😀".chars().collect();
+        let mut rng = rand::thread_rng();
+        (0..500)
+            .map(|_| {
+                let len = rng.gen_range(0, 300);
+                (0..len)
+                    .map(|_| pool[rng.gen_range(0, pool.len())])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_round_trip_random_trits() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let trits: Vec<Trit> = (0..rng.gen_range(0, 100) * 3)
+                .map(|_| rng.gen_range(-1, 2))
+                .collect();
+            let trytes = trytes(&trits).unwrap();
+            assert_eq!(trytes_to_trits(&trytes).unwrap(), trits);
+            assert_eq!(trytes.trits(), trits);
+        }
+    }
+
+    #[test]
+    fn should_round_trip_random_bytes_48() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let mut trits: Vec<Trit> = (0..HASH_TRINARY_SIZE)
+                .map(|_| rng.gen_range(-1, 2))
+                .collect();
+            trits[HASH_TRINARY_SIZE - 1] = 0;
+            let hash = trytes(&trits).unwrap();
+            let bytes = trytes_to_bytes_48(&hash).unwrap();
+            assert_eq!(bytes_48_to_trytes(&bytes), hash);
+
+            let mut bytes = [0; HASH_BYTES_SIZE];
+            rng.fill(&mut bytes[..]);
+            assert_eq!(bytes_48_to_trytes(&bytes).len(), HASH_TRYTES_SIZE);
+        }
+    }
+
+    #[test]
+    fn should_round_trip_random_values() {
+        let mut rng = rand::thread_rng();
+        let values = (0..1000)
+            .map(|_| rng.gen::<i64>())
+            .chain(vec![0, 1, -1, i64::MAX, i64::MIN]);
+        for value in values {
+            let trytes = value_to_trytes(value, 27);
+            assert_eq!(trytes_to_value(&trytes).unwrap(), value);
+            assert_eq!(crate::long_value(&value.trits()), value);
+        }
+    }
+
+    #[test]
+    fn should_convert_extreme_values() {
+        // The absolute value of i64::MIN used to overflow, and so did the partial sums decoding it
+        assert_eq!(
+            trytes_to_value(&value_to_trytes(i64::MIN, 27)).unwrap(),
+            i64::MIN
+        );
+        assert_eq!(crate::long_value(&i64::MIN.trits()), i64::MIN);
+        // Too many trits used to overflow, and now wrap around
+        let _ = crate::long_value(&[1; 60]);
+        let _ = crate::value(&[1; 10]);
+    }
+
+    #[test]
+    fn should_not_panic_on_invalid_trytes() {
+        for s in random_strings() {
+            let _ = trytes_to_trits(&s);
+            let _ = trytes_to_trits_into(&s, &mut Vec::new());
+            let _ = trytes_to_value(&s);
+            let _ = trytes_to_bytes_48(&s);
+            let _ = trytes_equal_ignoring_checksum(&s, ADDRESS);
+            let _ = normalize_tag(&s);
+            let _ = s.as_str().trits();
+        }
+        assert!(trytes_to_bytes_48(&"ä".repeat(40)).is_err());
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let trits: Vec<Trit> = (0..rng.gen_range(0, 100))
+                .map(|_| rng.gen_range(-3, 4))
+                .collect();
+            let _ = trytes(&trits);
+            let _ = crate::long_value(&trits);
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_address_length() {
+        assert!(trytes_equal_ignoring_checksum(&ADDRESS[..80], ADDRESS).is_err());
+        assert!(trytes_equal_ignoring_checksum(ADDRESS, "").is_err());
+    }
+
+    fn random_trytes(len: usize) -> String {
+        let mut rng = rand::thread_rng();
+        (0..len)
+            .map(|_| alphabet_char(rng.gen_range(0, 27)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn should_convert_long_trytes() {
+        let s = random_trytes(100_000);
+        let trits = s.trits();
+        assert_eq!(trits, trytes_to_trits(&s).unwrap());
+        assert_eq!(s.as_str().trits(), trits);
+        assert_eq!(trytes(&trits).unwrap(), s);
+        assert_eq!(trits.trytes().unwrap(), s);
+    }
+
+    #[test]
+    fn should_append_trytes() {
+        let mut out = String::from("IOTA");
+        trits_to_trytes_into(&"JOTA".trits(), &mut out).unwrap();
+        assert_eq!(out, "IOTAJOTA");
+        trits_to_trytes_into(&[], &mut out).unwrap();
+        assert_eq!(out, "IOTAJOTA");
+
+        assert!(trits_to_trytes_into(&[1, 0, 0, 1], &mut out).is_err());
+        assert_eq!(out, "IOTAJOTA");
+    }
+}
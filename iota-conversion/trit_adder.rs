@@ -0,0 +1,130 @@
+use crate::{Result, Trit};
+
+/// Sum of two trits without the carry, wrapping around in the balanced trit range
+///```
+/// use iota_conversion::trit_adder;
+///
+/// assert_eq!(trit_adder::sum_trits(1, 1), -1);
+/// assert_eq!(trit_adder::sum_trits(1, -1), 0);
+///```
+pub fn sum_trits(a: Trit, b: Trit) -> Trit {
+    match a + b {
+        2 => -1,
+        -2 => 1,
+        sum => sum,
+    }
+}
+
+/// Consensus of two trits, which is the carry of adding them: the trit if both are equal, 0 otherwise
+///```
+/// use iota_conversion::trit_adder;
+///
+/// assert_eq!(trit_adder::cons_trits(1, 1), 1);
+/// assert_eq!(trit_adder::cons_trits(1, 0), 0);
+///```
+pub fn cons_trits(a: Trit, b: Trit) -> Trit {
+    if a == b {
+        a
+    } else {
+        0
+    }
+}
+
+/// Sign of the sum of two trits
+fn any_trits(a: Trit, b: Trit) -> Trit {
+    (a + b).signum()
+}
+
+/// Adds two trits and a carry, returning the trit of the sum and the new carry
+fn full_add(a: Trit, b: Trit, carry: Trit) -> (Trit, Trit) {
+    let sum = sum_trits(a, b);
+    let next = any_trits(cons_trits(a, b), cons_trits(sum, carry));
+    (sum_trits(sum, carry), next)
+}
+
+/// Adds two balanced ternary numbers, least significant trit first. The result is as long as the
+/// longer input, plus one trit if the last addition carries over.
+///```
+/// use iota_conversion::{long_value, trit_adder};
+///
+/// let sum = trit_adder::add_trit_buffers(&[1, 1], &[1]).unwrap();
+/// assert_eq!(long_value(&sum), 5);
+///```
+pub fn add_trit_buffers(a: &[Trit], b: &[Trit]) -> Result<Vec<Trit>> {
+    ensure!(
+        a.iter().chain(b).all(|trit| (-1..=1).contains(trit)),
+        "Invalid trits: {:?} {:?}",
+        a,
+        b
+    );
+
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for i in 0..a.len().max(b.len()) {
+        let (trit, next) = full_add(
+            a.get(i).copied().unwrap_or(0),
+            b.get(i).copied().unwrap_or(0),
+            carry,
+        );
+        sum.push(trit);
+        carry = next;
+    }
+    if carry != 0 {
+        sum.push(carry);
+    }
+    Ok(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{long_value, Trinary};
+
+    #[test]
+    fn should_sum_trits() {
+        let table = [
+            (-1, -1, 1),
+            (-1, 0, -1),
+            (-1, 1, 0),
+            (0, -1, -1),
+            (0, 0, 0),
+            (0, 1, 1),
+            (1, -1, 0),
+            (1, 0, 1),
+            (1, 1, -1),
+        ];
+        for (a, b, sum) in table.iter() {
+            assert_eq!(sum_trits(*a, *b), *sum, "{} + {}", a, b);
+        }
+    }
+
+    #[test]
+    fn should_cons_trits() {
+        let table = [
+            (-1, -1, -1),
+            (-1, 0, 0),
+            (-1, 1, 0),
+            (0, -1, 0),
+            (0, 0, 0),
+            (0, 1, 0),
+            (1, -1, 0),
+            (1, 0, 0),
+            (1, 1, 1),
+        ];
+        for (a, b, cons) in table.iter() {
+            assert_eq!(cons_trits(*a, *b), *cons, "cons({}, {})", a, b);
+        }
+    }
+
+    #[test]
+    fn should_add_trit_buffers() {
+        for a in -40..=40i64 {
+            for b in -40..=40i64 {
+                let sum = add_trit_buffers(&a.trits(), &b.trits()).unwrap();
+                assert_eq!(long_value(&sum), a + b, "{} + {}", a, b);
+            }
+        }
+        assert_eq!(add_trit_buffers(&[1, 1], &[1, 1]).unwrap(), vec![-1, 0, 1]);
+        assert!(add_trit_buffers(&[2], &[0]).is_err());
+    }
+}
@@ -1,77 +1,249 @@
-use std::collections::HashMap;
+use std::fmt;
 
 use failure::Error;
 
 use iota_constants;
 
 use crate::Result;
+use crate::TRYTE_TO_TRITS_MAPPINGS;
+use crate::{alphabet_char, alphabet_index};
 
-lazy_static! {
-    static ref CHAR_TO_ASCII_MAP: HashMap<char, usize> = {
-        let mut res: HashMap<char, usize> = HashMap::new();
-        res.insert('\n', 10);
-        let mut ascii = 32;
-        for c in " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~".chars() {
-            res.insert(c, ascii);
-            ascii += 1;
-        }
-        res
-    };
-    static ref ASCII_TO_CHAR_MAP: HashMap<usize, char> = {
-        let mut res: HashMap<usize, char> = HashMap::new();
-        for (key, val) in CHAR_TO_ASCII_MAP.iter() {
-            res.insert(*val, *key);
-        }
-        res
-    };
+/// Errors of the conversions in this module.
+/// They are returned wrapped in `failure::Error`, use `downcast_ref::<TryteConverterError>()` to inspect them.
+#[derive(Debug)]
+pub enum TryteConverterError {
+    /// The string contains a character `to_trytes` can't encode
+    StringNotAscii {
+        /// The whole input
+        string: String,
+        /// Byte offset of the first unsupported character
+        index: usize,
+        /// Code point of the first unsupported character
+        codepoint: u32,
+    },
+    /// The string contains a character which isn't a tryte
+    StringNotTrytes {
+        /// The whole input
+        string: String,
+    },
+    /// The trits aren't valid trits of two trytes
+    TritsNotValid {
+        /// The invalid trits
+        trits: Vec<i8>,
+    },
 }
 
-#[derive(Debug, Fail)]
-enum TryteConverterError {
-    #[fail(display = "String [{}] is not valid ascii", string)]
-    StringNotAscii { string: String },
-    #[fail(display = "String [{}] is not valid trytes", string)]
-    StringNotTrytes { string: String },
+impl fmt::Display for TryteConverterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryteConverterError::StringNotAscii {
+                string,
+                index,
+                codepoint,
+            } => write!(
+                f,
+                "String [{}] is not valid ascii: U+{:04X} at index {}",
+                string, codepoint, index
+            ),
+            TryteConverterError::StringNotTrytes { string } => {
+                write!(f, "String [{}] is not valid trytes", string)
+            }
+            TryteConverterError::TritsNotValid { trits } => {
+                write!(f, "Trits {:?} are not valid", trits)
+            }
+        }
+    }
 }
 
-/// Converts a UTF-8 string containing ascii into a tryte-encoded string
+impl std::error::Error for TryteConverterError {}
+
+/// Converts a UTF-8 string containing ascii into a tryte-encoded string.
+/// All bytes from 0 to 126 are supported, including NUL and the other control characters.
+/// Bytes from 127 to 255 are rejected, as they don't fit into the two-tryte encoding of a character.
 pub fn to_trytes(input: &str) -> Result<String> {
-    let mut trytes = String::new();
-    let mut tmp_ascii = Vec::new();
+    let mut trytes = String::with_capacity(input.len() * 2);
+    for (index, c) in input.char_indices() {
+        // Supported characters map to their ascii values, so there's no need to look them up in a map.
+        let ascii = match c {
+            '\0'..='~' => c as u8,
+            _ => {
+                return Err(Error::from(TryteConverterError::StringNotAscii {
+                    string: input.to_string(),
+                    index,
+                    codepoint: c as u32,
+                }))
+            }
+        };
+        push_byte(&mut trytes, ascii);
+    }
+    Ok(trytes)
+}
+
+/// Converts a UTF-8 string into a tryte-encoded string like `to_trytes`, but encodes the fallback
+/// instead of every character `to_trytes` rejects.
+/// Fails with `StringNotAscii` if the fallback itself isn't supported by `to_trytes`.
+///```
+/// use iota_conversion::trytes_converter;
+///
+/// let trytes = trytes_converter::to_trytes_with_fallback("caf\u{e9}", '?').unwrap();
+/// assert_eq!(trytes, trytes_converter::to_trytes("caf?").unwrap());
+///```
+pub fn to_trytes_with_fallback(
+    input: &str,
+    fallback: char,
+) -> std::result::Result<String, TryteConverterError> {
+    if !('\0'..='~').contains(&fallback) {
+        return Err(TryteConverterError::StringNotAscii {
+            string: fallback.to_string(),
+            index: 0,
+            codepoint: fallback as u32,
+        });
+    }
+
+    let mut trytes = String::with_capacity(input.len() * 2);
     for c in input.chars() {
-        if let Some(ascii) = CHAR_TO_ASCII_MAP.get(&c) {
-            tmp_ascii.push(ascii);
-        } else {
-            return Err(Error::from(TryteConverterError::StringNotAscii {
-                string: input.to_string(),
-            }));
-        }
+        let ascii = match c {
+            '\0'..='~' => c as u8,
+            _ => fallback as u8,
+        };
+        push_byte(&mut trytes, ascii);
     }
-    for byte in tmp_ascii {
-        let mut ascii = *byte;
-        if ascii > 255 {
-            ascii = 32;
+    Ok(trytes)
+}
+
+fn push_byte(trytes: &mut String, b: u8) {
+    // Both indexes are below 27 for every byte.
+    let (first, second) = encode_byte(b);
+    trytes.push(alphabet_char(first as usize).unwrap());
+    trytes.push(alphabet_char(second as usize).unwrap());
+}
+
+/// Converts bytes into a tryte-encoded string, two trytes per byte like `to_trytes`.
+/// Unlike `to_trytes`, every byte is accepted, so arbitrary binary data can be encoded.
+/// Uses AVX2 if the CPU supports it.
+///```
+/// use iota_conversion::trytes_converter;
+///
+/// assert_eq!(trytes_converter::to_trytes_bytes(b"JOTA"), "TBYBCCKB");
+/// assert_eq!(trytes_converter::to_trytes_bytes(&[0, 255]), "99LI");
+///```
+pub fn to_trytes_bytes(bytes: &[u8]) -> String {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // AVX2 support was just checked
+            return unsafe { avx2::to_trytes_bytes(bytes) };
         }
-        let first = ascii % 27;
-        let second = (ascii - first) / 27;
-        trytes.push(iota_constants::TRYTE_ALPHABET[first]);
-        trytes.push(iota_constants::TRYTE_ALPHABET[second]);
     }
-    Ok(trytes)
+    to_trytes_bytes_scalar(bytes)
 }
 
-/// Converts a tryte-encoded string into a UTF-8 string containing ascii characters
-pub fn to_string(mut input_trytes: &str) -> Result<String> {
-    if input_trytes.len() % 2 != 0 {
-        input_trytes = &input_trytes[..input_trytes.len() - 1];
+fn to_trytes_bytes_scalar(bytes: &[u8]) -> String {
+    let mut trytes = String::with_capacity(bytes.len() * 2);
+    for &b in bytes {
+        push_byte(&mut trytes, b);
     }
-    let mut tmp = String::new();
+    trytes
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use super::to_trytes_bytes_scalar;
+
+    /// Encodes 16 bytes per iteration. Each byte is widened to 16 bits, which hold the index of the
+    /// first tryte in the low and the index of the second tryte in the high byte, so storing them
+    /// yields the trytes in output order.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn to_trytes_bytes(bytes: &[u8]) -> String {
+        let mut trytes = Vec::with_capacity(bytes.len() * 2);
+        let chunks = bytes.chunks_exact(16);
+        let rest = chunks.remainder();
+        let mut buf = [0u8; 32];
+        for chunk in chunks {
+            let b = _mm256_cvtepu8_epi16(_mm_loadu_si128(chunk.as_ptr() as *const __m128i));
+            // b * 19 >> 9 equals b / 27 for every byte
+            let second = _mm256_srli_epi16(_mm256_mullo_epi16(b, _mm256_set1_epi16(19)), 9);
+            let first = _mm256_sub_epi16(b, _mm256_mullo_epi16(second, _mm256_set1_epi16(27)));
+            let indexes = _mm256_or_si256(first, _mm256_slli_epi16(second, 8));
+            // Indexes 1 to 26 are 'A' to 'Z', index 0 is '9', which is 7 below '@'
+            let nines = _mm256_and_si256(
+                _mm256_cmpeq_epi8(indexes, _mm256_setzero_si256()),
+                _mm256_set1_epi8(7),
+            );
+            let chars = _mm256_sub_epi8(_mm256_add_epi8(indexes, _mm256_set1_epi8(64)), nines);
+            _mm256_storeu_si256(buf.as_mut_ptr() as *mut __m256i, chars);
+            trytes.extend_from_slice(&buf);
+        }
+        trytes.extend_from_slice(to_trytes_bytes_scalar(rest).as_bytes());
+        // Only tryte alphabet chars, which are ascii, have been written
+        String::from_utf8_unchecked(trytes)
+    }
+}
+
+/// Encodes a byte as the indexes of its two trytes in the tryte alphabet
+pub const fn encode_byte(b: u8) -> (u8, u8) {
+    (b % 27, b / 27)
+}
+
+/// Decodes the indexes of two trytes in the tryte alphabet into a byte.
+/// Only pairs produced by `encode_byte` decode to a meaningful value.
+pub const fn decode_byte(a: u8, b: u8) -> u8 {
+    (a as u16 + b as u16 * 27) as u8
+}
+
+/// What `to_string_with_meta` dropped from the end of the input
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodeMeta {
+    /// Number of trytes of the trailing NULs, two per NUL
+    pub trailing_padding_trytes: usize,
+    /// Whether the input had an odd number of trytes, whose last one doesn't decode to a character
+    pub odd_tryte_dropped: bool,
+}
+
+/// Converts a tryte-encoded string into a UTF-8 string containing ascii characters.
+/// Trailing NULs, which is what the `9` padding of fixed-width fields like messages decodes to,
+/// are dropped intentionally. Use `to_string_preserving_nulls` to keep them.
+pub fn to_string(input_trytes: &str) -> Result<String> {
+    to_string_with_meta(input_trytes).map(|(string, _)| string)
+}
+
+/// Converts a tryte-encoded string like `to_string`, and also reports how much of the input was
+/// dropped as padding, so content can be told apart from the padding of fixed-width fields.
+///```
+/// use iota_conversion::trytes_converter;
+///
+/// let (string, meta) = trytes_converter::to_string_with_meta("IC9999A").unwrap();
+/// assert_eq!(string, "Z");
+/// assert_eq!(meta.trailing_padding_trytes, 4);
+/// assert!(meta.odd_tryte_dropped);
+///```
+pub fn to_string_with_meta(input_trytes: &str) -> Result<(String, DecodeMeta)> {
+    let mut string = to_string_preserving_nulls(input_trytes)?;
+    let content = string.trim_end_matches('\0').len();
+    let meta = DecodeMeta {
+        trailing_padding_trytes: (string.len() - content) * 2,
+        odd_tryte_dropped: input_trytes.chars().count() % 2 == 1,
+    };
+    string.truncate(content);
+    Ok((string, meta))
+}
+
+/// Converts a tryte-encoded string into a UTF-8 string containing ascii characters, keeping NULs,
+/// so fixed-width fields decode to a predictable length.
+/// Pairs of trytes decoding to bytes from 0 to 126 are the inverse of `to_trytes`, so `99` decodes
+/// to NUL, while pairs outside of that range are skipped.
+pub fn to_string_preserving_nulls(input_trytes: &str) -> Result<String> {
+    let mut tmp = String::with_capacity(input_trytes.len() / 2);
+    // A trailing odd tryte is ignored. Chars are counted rather than bytes, so input which isn't
+    // ascii can't be split inside a char.
     let chars: Vec<char> = input_trytes.chars().collect();
-    for letters in chars.chunks(2) {
-        let first = match iota_constants::TRYTE_ALPHABET
-            .iter()
-            .position(|&x| x == letters[0])
-        {
+    for letters in chars.chunks_exact(2) {
+        let first = match alphabet_index(letters[0]) {
             Some(x) => x,
             None => {
                 return Err(Error::from(TryteConverterError::StringNotTrytes {
@@ -79,10 +251,7 @@ pub fn to_string(mut input_trytes: &str) -> Result<String> {
                 }))
             }
         };
-        let second = match iota_constants::TRYTE_ALPHABET
-            .iter()
-            .position(|&x| x == letters[1])
-        {
+        let second = match alphabet_index(letters[1]) {
             Some(x) => x,
             None => {
                 return Err(Error::from(TryteConverterError::StringNotTrytes {
@@ -91,10 +260,37 @@ pub fn to_string(mut input_trytes: &str) -> Result<String> {
             }
         };
         let decimal = first + second * 27;
-        if let Some(t) = ASCII_TO_CHAR_MAP.get(&decimal) {
-            tmp.push(*t);
+        if decimal <= 126 {
+            tmp.push(decimal as u8 as char);
+        }
+    }
+    Ok(tmp)
+}
+
+/// Converts trits into a UTF-8 string containing ascii characters, without going through trytes.
+/// Like `to_string`, trailing NULs are dropped.
+pub fn trits_to_string(trits: &[i8]) -> Result<String> {
+    ensure!(trits.len() % 6 == 0, "Invalid trit length: {}", trits.len());
+
+    let mut tmp = String::with_capacity(trits.len() / 6);
+    for chunk in trits.chunks(6) {
+        let (first, second) = chunk.split_at(iota_constants::TRITS_PER_TRYTE);
+        let value = |trits: &[i8]| {
+            TRYTE_TO_TRITS_MAPPINGS
+                .iter()
+                .position(|&x| x == trits)
+                .ok_or_else(|| {
+                    Error::from(TryteConverterError::TritsNotValid {
+                        trits: chunk.to_vec(),
+                    })
+                })
+        };
+        let decimal = value(first)? + value(second)? * 27;
+        if decimal <= 126 {
+            tmp.push(decimal as u8 as char);
         }
     }
+    tmp.truncate(tmp.trim_end_matches('\0').len());
     Ok(tmp)
 }
 
@@ -104,6 +300,7 @@ mod tests {
     use rand::{self, Rng};
 
     use super::*;
+    use crate::Trinary;
 
     #[test]
     fn should_convert_string_to_trytes() {
@@ -113,6 +310,23 @@ mod tests {
         assert_eq!(to_trytes(" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~").unwrap(), "EAFAGAHAIAJAKALAMANAOAPAQARASATAUAVAWAXAYAZA9BABBBCBDBEBFBGBHBIBJBKBLBMBNBOBPBQBRBSBTBUBVBWBXBYBZB9CACBCCCDCECFCGCHCICJCKCLCMCNCOCPCQCRCSCTCUCVCWCXCYCZC9DADBDCDDDEDFDGDHDIDJDKDLDMDNDODPDQDRD");
     }
 
+    #[test]
+    fn should_convert_bytes_to_trytes() {
+        let ascii: Vec<u8> = (0..=126).collect();
+        assert_eq!(
+            to_trytes_bytes(&ascii),
+            to_trytes(std::str::from_utf8(&ascii).unwrap()).unwrap()
+        );
+
+        let bytes: Vec<u8> = (0..=255).collect();
+        let trytes: Vec<char> = to_trytes_bytes(&bytes).chars().collect();
+        for (b, pair) in bytes.iter().zip(trytes.chunks_exact(2)) {
+            let first = alphabet_index(pair[0]).unwrap() as u8;
+            let second = alphabet_index(pair[1]).unwrap() as u8;
+            assert_eq!(decode_byte(first, second), *b);
+        }
+    }
+
     #[test]
     fn should_convert_trytes_to_string() {
         assert_eq!(to_string("IC").unwrap(), "Z");
@@ -122,6 +336,196 @@ mod tests {
         assert_eq!(to_string("EAFAGAHAIAJAKALAMANAOAPAQARASATAUAVAWAXAYAZA9BABBBCBDBEBFBGBHBIBJBKBLBMBNBOBPBQBRBSBTBUBVBWBXBYBZB9CACBCCCDCECFCGCHCICJCKCLCMCNCOCPCQCRCSCTCUCVCWCXCYCZC9DADBDCDDDEDFDGDHDIDJDKDLDMDNDODPDQDRD").unwrap(), " !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~");
     }
 
+    #[test]
+    fn should_convert_trits_to_string() {
+        assert_eq!(
+            trits_to_string(&to_trytes("hello").unwrap().trits()).unwrap(),
+            "hello"
+        );
+        assert!(trits_to_string(&[0; 5]).is_err());
+        assert!(trits_to_string(&[2, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn should_encode_and_decode_bytes() {
+        for b in 0..=126u8 {
+            let (first, second) = encode_byte(b);
+            let trytes: String = [
+                alphabet_char(first as usize).unwrap(),
+                alphabet_char(second as usize).unwrap(),
+            ]
+            .iter()
+            .collect();
+            assert_eq!(trytes, to_trytes(&(b as char).to_string()).unwrap());
+            assert_eq!(
+                to_string_preserving_nulls(&trytes).unwrap(),
+                (b as char).to_string()
+            );
+            assert_eq!(decode_byte(first, second), b);
+        }
+    }
+
+    #[test]
+    fn should_match_alphabet() {
+        let alphabet = iota_constants::TRYTE_ALPHABET;
+        assert_eq!(alphabet.len(), 27);
+        assert_eq!(alphabet[0], '9');
+        assert!(alphabet[1..].iter().copied().eq('A'..='Z'));
+
+        for b in 0..=126u8 {
+            let c = (b as char).to_string();
+            let trytes = to_trytes(&c).unwrap();
+            let expected: String = [alphabet[b as usize % 27], alphabet[b as usize / 27]]
+                .iter()
+                .collect();
+            assert_eq!(trytes, expected, "byte {}", b);
+            assert_eq!(to_string_preserving_nulls(&trytes).unwrap(), c);
+            if b != 0 {
+                assert_eq!(to_string(&trytes).unwrap(), c);
+            }
+        }
+    }
+
+    #[test]
+    fn should_convert_control_characters() {
+        let s = "tab\there\r\n\0end";
+        let trytes = to_trytes(s).unwrap();
+        assert_eq!(to_string(&trytes).unwrap(), s);
+        assert_eq!(trits_to_string(&trytes.trits()).unwrap(), s);
+        let trytes = to_trytes("\0\r\0\0").unwrap();
+        assert_eq!(to_string_preserving_nulls(&trytes).unwrap(), "\0\r\0\0");
+        assert_eq!(to_trytes("\0\t\r").unwrap(), "99I9M9");
+        assert!(to_trytes("\u{7f}").is_err());
+        assert!(to_trytes("ä").is_err());
+    }
+
+    #[test]
+    fn should_report_non_ascii_character() {
+        let err = to_trytes("caf\u{e9}!").unwrap_err();
+        match err.downcast_ref::<TryteConverterError>() {
+            Some(TryteConverterError::StringNotAscii {
+                index, codepoint, ..
+            }) => {
+                assert_eq!(*index, 3);
+                assert_eq!(*codepoint, 0xe9);
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn should_convert_with_fallback() {
+        assert_eq!(
+            to_trytes_with_fallback("caf\u{e9}", '?').unwrap(),
+            to_trytes("caf?").unwrap()
+        );
+        assert_eq!(
+            to_trytes_with_fallback("JOTA", '?').unwrap(),
+            to_trytes("JOTA").unwrap()
+        );
+        assert_eq!(
+            to_trytes_with_fallback("\u{7f}\u{1f600}", ' ').unwrap(),
+            "EAEA"
+        );
+    }
+
+    #[test]
+    fn should_reject_non_ascii_fallback() {
+        match to_trytes_with_fallback("caf\u{e9}", '\u{fffd}') {
+            Err(TryteConverterError::StringNotAscii {
+                string, codepoint, ..
+            }) => {
+                assert_eq!(string, "\u{fffd}");
+                assert_eq!(codepoint, 0xfffd);
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn should_report_padding() {
+        let padded = format!("{:9<27}", to_trytes("IOTA").unwrap());
+        let (string, meta) = to_string_with_meta(&padded).unwrap();
+        assert_eq!(string, "IOTA");
+        assert_eq!(
+            meta,
+            DecodeMeta {
+                trailing_padding_trytes: 18,
+                odd_tryte_dropped: true,
+            }
+        );
+
+        let (string, meta) = to_string_with_meta("TBYBCCKB").unwrap();
+        assert_eq!(string, "JOTA");
+        assert_eq!(meta, DecodeMeta::default());
+    }
+
+    #[test]
+    fn should_drop_padding_nulls() {
+        let padded = format!("{:9<27}", to_trytes("IOTA").unwrap());
+        assert_eq!(to_string(&padded).unwrap(), "IOTA");
+        assert_eq!(
+            trits_to_string(&padded[..26].to_string().trits()).unwrap(),
+            "IOTA"
+        );
+        let preserved = to_string_preserving_nulls(&padded).unwrap();
+        assert_eq!(preserved.len(), 13);
+        assert_eq!(preserved, format!("IOTA{}", "\0".repeat(9)));
+    }
+
+    /// Random strings of up to 100 chars taken from `pool`
+    fn random_strings(pool: &[char]) -> Vec<String> {
+        let mut rng = rand::thread_rng();
+        (0..500)
+            .map(|_| {
+                let len = rng.gen_range(0, 100);
+                (0..len)
+                    .map(|_| pool[rng.gen_range(0, pool.len())])
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_round_trip_all_ascii() {
+        let ascii: Vec<char> = (0..=126u8).map(char::from).collect();
+        for s in random_strings(&ascii) {
+            let trytes = to_trytes(&s).unwrap();
+            assert_eq!(trytes.len(), s.len() * 2);
+            assert_eq!(to_string_preserving_nulls(&trytes).unwrap(), s);
+            assert_eq!(to_string(&trytes).unwrap(), s.trim_end_matches('\0'));
+            assert_eq!(
+                trits_to_string(&trytes.trits()).unwrap(),
+                s.trim_end_matches('\0')
+            );
+        }
+    }
+
+    #[test]
+    fn should_not_panic_on_invalid_input() {
+        let pool: Vec<char> = "9ABZaz \0\u{7f}äé€😀".chars().collect();
+        for s in random_strings(&pool) {
+            let _ = to_trytes(&s);
+            let _ = to_string(&s);
+            let _ = to_string_preserving_nulls(&s);
+        }
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let trits: Vec<i8> = (0..rng.gen_range(0, 60))
+                .map(|_| rng.gen_range(-2, 3))
+                .collect();
+            let _ = trits_to_string(&trits);
+        }
+    }
+
+    #[test]
+    fn should_reject_odd_non_ascii_trytes() {
+        // Used to panic, since the odd length was counted in bytes
+        assert!(to_string("Aä").is_err());
+        assert_eq!(to_string("IC\u{e4}").unwrap(), "Z");
+    }
+
     #[test]
     fn should_convert_back_and_forth() {
         let s: String = rand::thread_rng()
@@ -132,4 +536,18 @@ mod tests {
         let back = to_string(&trytes).unwrap();
         assert_eq!(s, back);
     }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn should_encode_bytes_like_scalar_path() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let bytes: Vec<u8> = (0..rng.gen_range(0, 100)).map(|_| rng.gen()).collect();
+            let trytes = unsafe { avx2::to_trytes_bytes(&bytes) };
+            assert_eq!(trytes, to_trytes_bytes_scalar(&bytes), "{:?}", bytes);
+        }
+    }
 }
@@ -79,17 +79,17 @@ pub fn convert_amount_to(amount: u64, target: IotaUnits) -> f64 {
 pub fn find_optimal_iota_unit_to_display(amount: u64) -> IotaUnits {
     let length = amount.to_string().len();
 
-    if length >= 1 && length <= 3 {
+    if (1..=3).contains(&length) {
         IotaUnits::Iota
-    } else if length > 3 && length <= 6 {
+    } else if (4..=6).contains(&length) {
         IotaUnits::KiloIota
-    } else if length > 6 && length <= 9 {
+    } else if (7..=9).contains(&length) {
         IotaUnits::MegaIota
-    } else if length > 9 && length <= 12 {
+    } else if (10..=12).contains(&length) {
         IotaUnits::GigaIota
-    } else if length > 12 && length <= 15 {
+    } else if (13..=15).contains(&length) {
         IotaUnits::TeraIota
-    } else if length > 15 && length <= 18 {
+    } else if (16..=18).contains(&length) {
         IotaUnits::PetaIota
     } else {
         panic!("Invalid number")